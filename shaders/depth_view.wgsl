@@ -0,0 +1,40 @@
+struct DepthViewUniform {
+    znear: f32,
+    zfar: f32,
+    layer: i32,
+};
+
+@group(0) @binding(0)
+var<uniform> depth_view: DepthViewUniform;
+@group(0) @binding(1)
+var scene_depth: texture_2d<f32>;
+@group(0) @binding(2)
+var shadow_map: texture_2d_array<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn grayscale(depth: f32) -> vec4<f32> {
+    let value = 1.0 - clamp(depth, 0.0, 1.0);
+    return vec4<f32>(value, value, value, 1.0);
+}
+
+// Perspective depth is linearized first, otherwise everything past a few
+// blocks would come out white.
+@fragment
+fn fs_scene(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let depth = textureLoad(scene_depth, vec2<i32>(position.xy), 0).r;
+    let znear = depth_view.znear;
+    let zfar = depth_view.zfar;
+    let linear = znear * zfar / (zfar - depth * (zfar - znear));
+    return grayscale((linear - znear) / (zfar - znear));
+}
+
+// Shadow cascades are orthographic, so their depth is already linear.
+@fragment
+fn fs_shadow(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return grayscale(textureLoad(shadow_map, vec2<i32>(position.xy), depth_view.layer, 0).r);
+}
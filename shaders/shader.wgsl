@@ -1,9 +1,12 @@
 struct ShadowUniforms {
-    light_view_proj: mat4x4<f32>,
+    light_view_proj: array<mat4x4<f32>, 4>,
+    splits: vec4<f32>,
+    // x: cascade count, y: texel size
+    params: vec4<f32>,
 };
 
 @group(2) @binding(0)
-var shadow_map: texture_depth_2d;
+var shadow_map: texture_depth_2d_array;
 @group(2) @binding(1)
 var shadow_sampler: sampler_comparison;
 @group(2) @binding(2)
@@ -14,6 +17,14 @@ struct CameraUniform {
     camera_pos: vec4<f32>,
 };
 
+struct SkyUniform {
+    inv_view_proj: mat4x4<f32>,
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    fog: vec4<f32>,
+};
+
 struct LightUniform {
     position: vec4<f32>,
     color: vec4<f32>,
@@ -25,21 +36,33 @@ struct LightUniform {
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
+@group(0) @binding(1)
+var<uniform> sky: SkyUniform;
 
 @group(1) @binding(0)
 var<uniform> light: LightUniform;
 
+@group(3) @binding(0)
+var block_textures: texture_2d_array<f32>;
+@group(3) @binding(1)
+var block_sampler: sampler;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    @location(1) color: vec4<f32>,
     @location(2) normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(4) texture: i32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
     @location(1) world_position: vec3<f32>,
     @location(2) normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(4) @interpolate(flat) texture: i32,
+    @location(5) view_depth: f32,
 };
 
 @vertex
@@ -49,6 +72,9 @@ fn vs_main(model: VertexInput) -> VertexOutput {
     out.world_position = model.position;
     out.normal = model.normal;
     out.color = model.color;
+    out.uv = model.uv;
+    out.texture = model.texture;
+    out.view_depth = out.clip_position.w;
     return out;
 }
 
@@ -56,32 +82,79 @@ fn gamma_correction(color: vec3<f32>) -> vec3<f32> {
     return pow(color, vec3<f32>(1.0/2.2));
 }
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let light_space_position = shadow_uniforms.light_view_proj * vec4<f32>(in.world_position, 1.0);
+// Fades distant terrain into the horizon color so chunks at the edge of the
+// loaded area don't pop in.
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let distance = length(world_position - camera.camera_pos.xyz);
+    let fog = smoothstep(sky.fog.x, sky.fog.y, distance);
+    return mix(color, sky.horizon_color.rgb, fog);
+}
+
+// Blocks without a texture layer (-1) fall back to their flat vertex color;
+// textured blocks carry only the face shading in `color`.
+fn block_albedo(in: VertexOutput) -> vec4<f32> {
+    let texel = textureSample(block_textures, block_sampler, in.uv, max(in.texture, 0));
+    return select(in.color, texel * in.color, in.texture >= 0);
+}
+
+fn select_cascade(view_depth: f32) -> i32 {
+    let count = i32(shadow_uniforms.params.x);
+    for (var i = 0; i < count; i++) {
+        if view_depth < shadow_uniforms.splits[i] {
+            return i;
+        }
+    }
+    return -1;
+}
+
+// 3x3 PCF over the cascade covering this fragment. Anything past the last
+// cascade or outside its projection is treated as lit.
+fn sample_shadow(world_position: vec3<f32>, view_depth: f32) -> f32 {
+    let cascade = select_cascade(view_depth);
+    if cascade < 0 {
+        return 1.0;
+    }
+
+    let light_space_position = shadow_uniforms.light_view_proj[cascade] * vec4<f32>(world_position, 1.0);
     let shadow_coords = light_space_position.xyz / light_space_position.w;
-    let shadow_xy = shadow_coords.xy * 0.5 + 0.5;
-    
-    let shadow_comparison = shadow_coords.z - 0.005;
-    let shadow = textureSampleCompare(
-        shadow_map,
-        shadow_sampler,
-        shadow_xy,
-        shadow_comparison
-    );
+    let shadow_uv = vec2<f32>(shadow_coords.x * 0.5 + 0.5, shadow_coords.y * -0.5 + 0.5);
+    if any(shadow_uv < vec2<f32>(0.0)) || any(shadow_uv > vec2<f32>(1.0)) || shadow_coords.z > 1.0 {
+        return 1.0;
+    }
+
+    let texel_size = shadow_uniforms.params.y;
+    let comparison = shadow_coords.z - 0.001;
+    var shadow = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
+            shadow += textureSampleCompareLevel(
+                shadow_map,
+                shadow_sampler,
+                shadow_uv + offset,
+                cascade,
+                comparison
+            );
+        }
+    }
+    return shadow / 9.0;
+}
+
+fn shade(in: VertexOutput, albedo: vec3<f32>) -> vec3<f32> {
+    let shadow = sample_shadow(in.world_position, in.view_depth);
 
     let N = normalize(in.normal);
     let L = normalize(-light.direction.xyz);
     let V = normalize(camera.camera_pos.xyz - in.world_position);
     let H = normalize(L + V);
     
-    let ambient = light.ambient.xyz * in.color;
+    let ambient = light.ambient.xyz * albedo;
     
     let wrap = 0.5;
     let NdotL = dot(N, L);
     let diffuse_strength = clamp((NdotL + wrap) / (1.0 + wrap), 0.0, 1.0);
     
-    let diffuse = light.color.xyz * diffuse_strength * in.color;
+    let diffuse = light.color.xyz * diffuse_strength * albedo;
     
     let NdotH = max(dot(N, H), 0.0);
     let specular_strength = pow(NdotH, light.params.y);
@@ -94,6 +167,23 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let lit = diffuse + specular;
     result += lit * shadow;
     
-    result = gamma_correction(result);
+    return gamma_correction(apply_fog(result, in.world_position));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let albedo = block_albedo(in);
+    let result = shade(in, albedo.rgb);
+
+    // Cutout blocks such as leaves are alpha tested instead of blended.
+    if albedo.a < 0.5 {
+        discard;
+    }
     return vec4<f32>(result, 1.0);
 }
+
+@fragment
+fn fs_transparent(in: VertexOutput) -> @location(0) vec4<f32> {
+    let albedo = block_albedo(in);
+    return vec4<f32>(shade(in, albedo.rgb), albedo.a);
+}
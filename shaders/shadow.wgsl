@@ -7,7 +7,7 @@ var<uniform> light_view_proj: LightViewProj;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    @location(1) color: vec4<f32>,
     @location(2) normal: vec3<f32>,
 };
 
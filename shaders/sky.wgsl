@@ -0,0 +1,74 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+};
+
+struct SkyUniform {
+    inv_view_proj: mat4x4<f32>,
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    fog: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(0) @binding(1)
+var<uniform> sky: SkyUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+// A single triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+fn hash(p: vec3<f32>) -> f32 {
+    let q = fract(p * vec3<f32>(0.1031, 0.1030, 0.0973));
+    let r = q + dot(q, q.yxz + 33.33);
+    return fract((r.x + r.y) * r.z);
+}
+
+fn stars(dir: vec3<f32>) -> f32 {
+    let cell = floor(dir * 300.0);
+    let brightness = hash(cell);
+    return select(0.0, (brightness - 0.997) / 0.003, brightness > 0.997);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let dir = normalize(far.xyz / far.w - camera.camera_pos.xyz);
+    let sun = sky.sun_direction.xyz;
+    let twilight = sky.sun_direction.w;
+    let night = sky.fog.z;
+
+    let height = max(dir.y, 0.0);
+    var color = mix(sky.horizon_color.rgb, sky.zenith_color.rgb, pow(height, 0.5));
+    color = select(color, sky.horizon_color.rgb * 0.6, dir.y < -0.05);
+
+    let sun_dot = dot(dir, sun);
+    let glow = pow(max(sun_dot, 0.0), 8.0) * twilight;
+    color += vec3<f32>(1.0, 0.45, 0.15) * glow * 0.6;
+
+    let sun_disc = smoothstep(0.9985, 0.9995, sun_dot);
+    color = mix(color, vec3<f32>(1.0, 0.95, 0.8), sun_disc);
+
+    let moon_dot = dot(dir, -sun);
+    let moon_disc = smoothstep(0.9990, 0.9996, moon_dot);
+    color = mix(color, vec3<f32>(0.85, 0.88, 0.95), moon_disc * night);
+
+    color += vec3<f32>(stars(dir)) * night * step(0.0, dir.y);
+
+    return vec4<f32>(pow(color, vec3<f32>(1.0 / 2.2)), 1.0);
+}
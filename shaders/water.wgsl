@@ -0,0 +1,116 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+};
+
+struct SkyUniform {
+    inv_view_proj: mat4x4<f32>,
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    fog: vec4<f32>,
+};
+
+struct LightUniform {
+    position: vec4<f32>,
+    color: vec4<f32>,
+    direction: vec4<f32>,
+    ambient: vec4<f32>,
+    attenuation: vec4<f32>,
+    params: vec4<f32>,
+}
+
+struct WaterUniform {
+    time: f32,
+    znear: f32,
+    zfar: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(0) @binding(1)
+var<uniform> sky: SkyUniform;
+
+@group(1) @binding(0)
+var<uniform> light: LightUniform;
+
+@group(2) @binding(0)
+var<uniform> water: WaterUniform;
+@group(2) @binding(1)
+var scene_depth: texture_2d<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(4) texture: i32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+const WAVE_AMPLITUDE: f32 = 0.04;
+const SHALLOW_COLOR: vec3<f32> = vec3<f32>(0.1, 0.55, 0.7);
+const DEEP_COLOR: vec3<f32> = vec3<f32>(0.0, 0.12, 0.35);
+
+// Two crossing sine waves; returns the height offset and its x/z slopes.
+fn wave(p: vec2<f32>, t: f32) -> vec3<f32> {
+    let a = p.x * 0.8 + p.y * 0.3 + t * 1.6;
+    let b = p.y * 1.1 - p.x * 0.4 + t * 1.2;
+    let height = WAVE_AMPLITUDE * (sin(a) + sin(b));
+    let dx = WAVE_AMPLITUDE * (0.8 * cos(a) - 0.4 * cos(b));
+    let dz = WAVE_AMPLITUDE * (0.3 * cos(a) + 1.1 * cos(b));
+    return vec3<f32>(height, dx, dz);
+}
+
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let distance = length(world_position - camera.camera_pos.xyz);
+    let fog = smoothstep(sky.fog.x, sky.fog.y, distance);
+    return mix(color, sky.horizon_color.rgb, fog);
+}
+
+fn linearize_depth(depth: f32) -> f32 {
+    return water.znear * water.zfar / (water.zfar - depth * (water.zfar - water.znear));
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    let w = wave(model.position.xz, water.time);
+    let position = model.position + vec3<f32>(0.0, w.x - WAVE_AMPLITUDE * 2.0, 0.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.world_position = position;
+    out.normal = normalize(vec3<f32>(-w.y, 1.0, -w.z));
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let w = wave(in.world_position.xz, water.time);
+    let N = normalize(vec3<f32>(-w.y, 1.0, -w.z));
+    let V = normalize(camera.camera_pos.xyz - in.world_position);
+    let L = normalize(-light.direction.xyz);
+    let H = normalize(L + V);
+
+    let scene = linearize_depth(textureLoad(scene_depth, vec2<i32>(in.clip_position.xy), 0).r);
+    let surface = linearize_depth(in.clip_position.z);
+    let thickness = clamp((scene - surface) / 8.0, 0.0, 1.0);
+
+    let base = mix(SHALLOW_COLOR, DEEP_COLOR, thickness);
+    let lit = base * (light.ambient.xyz + light.color.xyz * max(dot(N, L), 0.0));
+
+    let cos_theta = clamp(dot(N, V), 0.0, 1.0);
+    let fresnel = 0.02 + 0.98 * pow(1.0 - cos_theta, 5.0);
+    let R = reflect(-V, N);
+    let reflected = mix(sky.horizon_color.rgb, sky.zenith_color.rgb, sqrt(max(R.y, 0.0)));
+    let specular = light.color.xyz * pow(max(dot(N, H), 0.0), 128.0) * 1.5;
+
+    let color = apply_fog(mix(lit, reflected, fresnel) + specular, in.world_position);
+    let alpha = clamp(mix(0.45, 0.9, thickness) + fresnel * 0.3, 0.0, 1.0);
+    return vec4<f32>(pow(color, vec3<f32>(1.0 / 2.2)), alpha);
+}
@@ -1,3 +1,4 @@
+use crate::world::player::PlayerInput;
 use glam::{Mat4, Vec3};
 use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 
@@ -11,6 +12,8 @@ pub struct Camera {
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
+    /// Applied after the projection; used to render one tile of a larger image.
+    pub tile_transform: Mat4,
 }
 
 #[repr(C)]
@@ -45,19 +48,24 @@ impl Camera {
             fovy: 70.0,
             znear: 0.1,
             zfar: 200.0,
+            tile_transform: Mat4::IDENTITY,
         }
     }
 
-    fn get_view_direction(&self) -> Vec3 {
+    pub fn get_view_direction(&self) -> Vec3 {
         let (yaw_sin, yaw_cos) = self.yaw.to_radians().sin_cos();
         let (pitch_sin, pitch_cos) = self.pitch.to_radians().sin_cos();
         Vec3::new(yaw_cos * pitch_cos, pitch_sin, yaw_sin * pitch_cos).normalize()
     }
 
+    pub fn build_view_matrix(&self) -> Mat4 {
+        Mat4::look_to_rh(self.position, self.get_view_direction(), self.up)
+    }
+
     pub fn build_view_projection_matrix(&self) -> Mat4 {
-        let view = Mat4::look_to_rh(self.position, self.get_view_direction(), self.up);
+        let view = self.build_view_matrix();
         let proj = Mat4::perspective_rh(self.fovy.to_radians(), self.aspect, self.znear, self.zfar);
-        proj * view
+        self.tile_transform * proj * view
     }
 }
 pub struct CameraController {
@@ -146,27 +154,52 @@ impl CameraController {
                 }
                 true
             }
-            WindowEvent::CursorMoved { position, .. } => {
-                if self.mouse_pressed {
-                    if let Some((last_x, last_y)) = self.last_mouse_pos {
-                        let dx = position.x - last_x;
-                        let dy = position.y - last_y;
-
-                        camera.yaw -= dx as f32 * self.sensitivity;
-                        camera.pitch =
-                            (camera.pitch - dy as f32 * self.sensitivity).clamp(-89.0, 89.0);
-                    }
-                    self.last_mouse_pos = Some((position.x, position.y));
-                    true
-                } else {
-                    false
+            WindowEvent::CursorMoved { position, .. } if self.mouse_pressed => {
+                if let Some((last_x, last_y)) = self.last_mouse_pos {
+                    let dx = position.x - last_x;
+                    let dy = position.y - last_y;
+
+                    camera.yaw -= dx as f32 * self.sensitivity;
+                    camera.pitch = (camera.pitch - dy as f32 * self.sensitivity).clamp(-89.0, 89.0);
                 }
+                self.last_mouse_pos = Some((position.x, position.y));
+                true
             }
             _ => false,
         }
     }
 
-    pub fn update_camera(&mut self, camera: &mut Camera) {
+    /// The held movement keys as input for a player walking the way `camera`
+    /// faces.
+    pub fn player_input(&self, camera: &Camera) -> PlayerInput {
+        let (yaw_sin, yaw_cos) = camera.yaw.to_radians().sin_cos();
+        let forward = Vec3::new(yaw_cos, 0.0, yaw_sin);
+        let right = forward.cross(Vec3::Y);
+
+        let mut direction = Vec3::ZERO;
+        if self.is_forward_pressed {
+            direction += forward;
+        }
+        if self.is_backward_pressed {
+            direction -= forward;
+        }
+        if self.is_right_pressed {
+            direction += right;
+        }
+        if self.is_left_pressed {
+            direction -= right;
+        }
+
+        PlayerInput {
+            direction,
+            jump: self.is_up_pressed,
+            descend: self.is_down_pressed,
+            run: self.is_run_pressed,
+        }
+    }
+
+    /// `speed` is in blocks per second, `dt` in seconds.
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
         let forward = camera.get_view_direction();
         let right = forward.cross(camera.up).normalize();
 
@@ -201,6 +234,6 @@ impl CameraController {
             self.speed
         };
 
-        camera.position += velocity * actual_speed;
+        camera.position += velocity * actual_speed * dt;
     }
 }
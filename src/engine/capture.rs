@@ -0,0 +1,225 @@
+use glam::{Mat4, Vec3};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+pub const DEPTH_VIEW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    Color,
+    /// Linearized scene depth as a grayscale image.
+    Depth,
+    ShadowMap {
+        cascade: u32,
+    },
+    /// Renders the frame in `tiles` x `tiles` pieces and stitches them together.
+    Tiled {
+        tiles: u32,
+    },
+}
+
+impl Capture {
+    fn name(&self) -> &'static str {
+        match self {
+            Capture::Color => "screenshot",
+            Capture::Depth => "depth",
+            Capture::ShadowMap { .. } => "shadow",
+            Capture::Tiled { .. } => "hires",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Surface(wgpu::SurfaceError),
+    Readback(wgpu::BufferAsyncError),
+    Image(image::ImageError),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaptureError::Surface(e) => write!(f, "failed to render frame: {}", e),
+            CaptureError::Readback(e) => write!(f, "failed to read back texture: {}", e),
+            CaptureError::Image(e) => write!(f, "failed to write image: {}", e),
+            CaptureError::Io(e) => write!(f, "failed to create screenshot directory: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<wgpu::SurfaceError> for CaptureError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        CaptureError::Surface(e)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for CaptureError {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        CaptureError::Readback(e)
+    }
+}
+
+impl From<image::ImageError> for CaptureError {
+    fn from(e: image::ImageError) -> Self {
+        CaptureError::Image(e)
+    }
+}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+/// A color texture that frames can be rendered into and copied out of.
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OffscreenTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            format,
+            width,
+            height,
+        }
+    }
+
+    /// Reads the target back as RGBA8, swizzling BGRA surface formats.
+    pub fn read_rgba(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let mut pixels = read_texture(device, queue, &self.texture, self.width, self.height)?;
+
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixels)
+    }
+}
+
+/// Copies a 4-byte-per-texel color texture into tightly packed rows.
+fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, CaptureError> {
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+    let padded = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    for row in padded.chunks(padded_bytes_per_row as usize) {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    drop(padded);
+    buffer.unmap();
+
+    Ok(pixels)
+}
+
+/// Post-projection transform that zooms into tile (`x`, `y`) of a
+/// `tiles` x `tiles` grid, counting from the top-left.
+pub fn tile_transform(tiles: u32, x: u32, y: u32) -> Mat4 {
+    let n = tiles as f32;
+    let offset_x = n - 1.0 - 2.0 * x as f32;
+    let offset_y = -(n - 1.0 - 2.0 * y as f32);
+    Mat4::from_translation(Vec3::new(offset_x, offset_y, 0.0))
+        * Mat4::from_scale(Vec3::new(n, n, 1.0))
+}
+
+/// A fresh, timestamped path inside `dir` for the given capture.
+pub fn capture_path(dir: impl AsRef<Path>, capture: Capture) -> Result<PathBuf, CaptureError> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+    Ok(dir.join(format!("{}-{}.png", capture.name(), timestamp)))
+}
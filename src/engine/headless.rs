@@ -0,0 +1,202 @@
+use crate::engine::camera::Camera;
+use crate::engine::capture::{CaptureError, OffscreenTarget};
+use crate::engine::gpu::request_device;
+use crate::engine::light::Light;
+use crate::engine::renderer::Renderer;
+use crate::engine::shadow::ShadowConfig;
+use crate::engine::sky::Sky;
+use crate::engine::texture::BlockTextures;
+use crate::world::chunk::{Chunk, ChunkMesh, ChunkNeighbors, ChunkPos, CHUNK_SIZE};
+use glam::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    Capture(CaptureError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no compatible graphics adapter was found"),
+            HeadlessError::Capture(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<CaptureError> for HeadlessError {
+    fn from(e: CaptureError) -> Self {
+        HeadlessError::Capture(e)
+    }
+}
+
+impl From<wgpu::SurfaceError> for HeadlessError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        HeadlessError::Capture(e.into())
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// Renders into an offscreen texture instead of a window surface, so frames
+/// can be produced and inspected on machines with no display.
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: Renderer,
+    target: OffscreenTarget,
+}
+
+impl HeadlessRenderer {
+    /// `force_fallback_adapter` asks wgpu for a software adapter (e.g. llvmpipe
+    /// or WARP), which is what CI machines without a GPU have to use.
+    pub async fn new(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+        camera: &Camera,
+    ) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+        let (_, device, queue) = request_device(&instance, None, force_fallback_adapter)
+            .await
+            .ok_or(HeadlessError::NoAdapter)?;
+
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            HEADLESS_FORMAT,
+            width,
+            height,
+            camera,
+            ShadowConfig::default(),
+        );
+
+        let target = OffscreenTarget::new(&device, HEADLESS_FORMAT, width, height);
+
+        Ok(Self {
+            device,
+            queue,
+            renderer,
+            target,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.target.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.target.height
+    }
+
+    pub fn block_textures(&self) -> &BlockTextures {
+        self.renderer.block_textures()
+    }
+
+    /// Draws one frame of the chunk `meshes` and returns it as tightly packed
+    /// RGBA8 rows.
+    pub fn render(
+        &mut self,
+        camera: &Camera,
+        light: &Light,
+        sky: &Sky,
+        meshes: &[(ChunkPos, ChunkMesh)],
+    ) -> Result<Vec<u8>, HeadlessError> {
+        self.renderer.update_light_buffer(&self.queue, light);
+        self.renderer.update_sky_buffer(&self.queue, &sky.uniform);
+        for (pos, mesh) in meshes {
+            self.renderer.upload_chunk_mesh(&self.device, *pos, mesh);
+        }
+        self.renderer
+            .render(&self.target.view, &self.device, &self.queue, camera)?;
+
+        Ok(self.target.read_rgba(&self.device, &self.queue)?)
+    }
+}
+
+/// A fixed camera, midday light and the terrain around it, generated
+/// synchronously so the result only depends on the world generator.
+pub struct TerrainScene {
+    pub camera: Camera,
+    pub light: Light,
+    pub sky: Sky,
+    pub chunks: HashMap<ChunkPos, Chunk>,
+}
+
+impl TerrainScene {
+    pub fn new(width: u32, height: u32, radius: i32) -> Self {
+        let camera = Camera::new(Vec3::new(8.0, 110.0, 8.0), width as f32 / height as f32);
+
+        let mut light = Light::new(
+            Vec3::new(0.0, 100.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-0.5, -1.0, -0.3).normalize(),
+        );
+        light.update();
+
+        let fog_end = (radius * CHUNK_SIZE as i32) as f32;
+        let mut sky = Sky::new(fog_end * 0.6, fog_end);
+        sky.sun_direction = -light.direction;
+        sky.update(&camera);
+
+        let center = ChunkPos::from_world_pos(camera.position);
+        let mut chunks = HashMap::new();
+        for y in -2..=2 {
+            for x in -radius..=radius {
+                for z in -radius..=radius {
+                    let pos = ChunkPos::new(center.x + x, center.y + y, center.z + z);
+                    let mut chunk = Chunk::new(pos.to_world_pos());
+                    chunk.generate_terrain(pos.to_world_pos());
+                    chunks.insert(pos, chunk);
+                }
+            }
+        }
+
+        Self {
+            camera,
+            light,
+            sky,
+            chunks,
+        }
+    }
+
+    /// Meshes every chunk against its neighbors, as the worker threads do.
+    pub fn meshes(&self, textures: &BlockTextures) -> Vec<(ChunkPos, ChunkMesh)> {
+        self.chunks
+            .iter()
+            .map(|(pos, chunk)| {
+                let neighbors = pos.neighbors().map(|pos| self.chunks.get(&pos).cloned());
+                let mesh = chunk.generate_mesh(&ChunkNeighbors::new(neighbors), textures);
+                (*pos, mesh)
+            })
+            .collect()
+    }
+}
+
+/// Fraction of pixels whose largest channel difference exceeds `tolerance`.
+/// Software and hardware rasterizers disagree slightly at edges, so golden
+/// images are compared with a small per-channel tolerance.
+pub fn image_difference(a: &[u8], b: &[u8], tolerance: u8) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 1.0;
+    }
+
+    let differing = a
+        .chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .filter(|(pa, pb)| {
+            pa.iter()
+                .zip(pb.iter())
+                .any(|(ca, cb)| ca.abs_diff(*cb) > tolerance)
+        })
+        .count();
+
+    differing as f32 / (a.len() / 4) as f32
+}
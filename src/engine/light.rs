@@ -42,5 +42,7 @@ impl Light {
     pub fn update(&mut self) {
         self.uniform.position = [self.position.x, self.position.y, self.position.z, 1.0];
         self.uniform.direction = [self.direction.x, self.direction.y, self.direction.z, 1.0];
+        self.uniform.color = [self.color.x, self.color.y, self.color.z, 1.0];
+        self.uniform.ambient = [self.ambient.x, self.ambient.y, self.ambient.z, 1.0];
     }
 }
@@ -0,0 +1,87 @@
+use crate::utils::aabb::Aabb;
+use glam::Vec3;
+
+pub const OUTLINE_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+// The crosshair pipeline inverts whatever is behind it, so it's drawn white.
+const CROSSHAIR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+/// Half the length of each crosshair arm, in pixels.
+const CROSSHAIR_SIZE: f32 = 10.0;
+// Pushes the outline slightly off the block so it doesn't z-fight its faces.
+const OUTLINE_OFFSET: f32 = 0.002;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// The twelve edges of `bounds`, slightly enlarged, as a line list.
+pub fn box_lines(bounds: &Aabb, color: [f32; 4]) -> [LineVertex; 24] {
+    let min = bounds.min - Vec3::splat(OUTLINE_OFFSET);
+    let max = bounds.max + Vec3::splat(OUTLINE_OFFSET);
+    let corner = |i: usize| {
+        [
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        ]
+    };
+
+    // Corner indices differing in exactly one bit share an edge.
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+
+    let mut vertices = [LineVertex {
+        position: [0.0; 3],
+        color,
+    }; 24];
+    for (edge, (a, b)) in EDGES.iter().enumerate() {
+        vertices[edge * 2].position = corner(*a);
+        vertices[edge * 2 + 1].position = corner(*b);
+    }
+    vertices
+}
+
+/// A plus sign in the middle of a `width` x `height` screen, in normalized
+/// device coordinates so its arms stay the same length in pixels.
+pub fn crosshair_lines(width: u32, height: u32) -> [LineVertex; 4] {
+    let x = CROSSHAIR_SIZE * 2.0 / width.max(1) as f32;
+    let y = CROSSHAIR_SIZE * 2.0 / height.max(1) as f32;
+    let vertex = |position| LineVertex {
+        position,
+        color: CROSSHAIR_COLOR,
+    };
+
+    [
+        vertex([-x, 0.0, 0.0]),
+        vertex([x, 0.0, 0.0]),
+        vertex([0.0, -y, 0.0]),
+        vertex([0.0, y, 0.0]),
+    ]
+}
@@ -1,4 +1,13 @@
 pub mod camera;
+pub mod capture;
+pub mod gpu;
+pub mod headless;
+pub mod light;
+pub mod lines;
 pub mod renderer;
+pub mod settings;
+pub mod shadow;
+pub mod sky;
 pub mod state;
-pub mod light;
+pub mod texture;
+pub mod timestep;
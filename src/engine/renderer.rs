@@ -1,103 +1,36 @@
 use crate::engine::camera::{Camera, CameraUniform};
+use crate::engine::capture::DEPTH_VIEW_FORMAT;
 use crate::engine::light::Light;
-use glam::{Mat4, Vec3};
+use crate::engine::lines::{box_lines, crosshair_lines, LineVertex, OUTLINE_COLOR};
+use crate::engine::shadow::{compute_cascades, ShadowConfig, ShadowMap, MAX_CASCADES};
+use crate::engine::sky::SkyUniform;
+use crate::engine::texture::{BlockTextures, Texture, BLOCK_TEXTURE_DIR};
+use crate::utils::aabb::Aabb;
+use crate::utils::frustum::Frustum;
+use crate::world::chunk::{ChunkMesh, ChunkPos, CHUNK_SIZE};
+use glam::Vec3;
+use std::collections::HashMap;
+use std::sync::Arc;
 use wgpu::util::DeviceExt;
 
-pub struct Texture {
-    pub texture: wgpu::Texture,
-    pub view: wgpu::TextureView,
-    pub sampler: wgpu::Sampler,
-}
-
-impl Texture {
-    pub fn create_depth_texture(
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-    ) -> Self {
-        let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
-            depth_or_array_layers: 1,
-        };
-
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            compare: Some(wgpu::CompareFunction::LessEqual),
-            ..Default::default()
-        });
-
-        Self {
-            texture,
-            view,
-            sampler,
-        }
-    }
-
-    pub fn create_shadow_texture(device: &wgpu::Device) -> Self {
-        let size = 4096;
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Shadow Texture"),
-            size: wgpu::Extent3d {
-                width: size,
-                height: size,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            compare: Some(wgpu::CompareFunction::LessEqual),
-            ..Default::default()
-        });
-
-        Self {
-            texture,
-            view,
-            sampler,
-        }
-    }
-}
-
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
-    pub color: [f32; 3],
+    pub color: [f32; 4],
     pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub texture: i32,
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x4,
+        2 => Float32x3,
+        3 => Float32x2,
+        4 => Sint32
+    ];
 
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -110,35 +43,129 @@ impl Vertex {
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct ShadowUniforms {
-    light_view_proj: [[f32; 4]; 4],
+pub struct WaterUniform {
+    time: f32,
+    znear: f32,
+    zfar: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DepthViewUniform {
+    znear: f32,
+    zfar: f32,
+    layer: i32,
+    _padding: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DepthSource {
+    Scene,
+    ShadowCascade(u32),
+}
+
+struct MeshBuffer {
+    buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl MeshBuffer {
+    fn new(device: &wgpu::Device, label: &str, vertices: &[Vertex]) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
+            buffer,
+            num_vertices: vertices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
+
+/// The uploaded mesh of one chunk. Empty layers get no buffer.
+struct ChunkBuffers {
+    min: Vec3,
+    max: Vec3,
+    opaque: Option<MeshBuffer>,
+    transparent: Option<MeshBuffer>,
+    water: Option<MeshBuffer>,
+}
+
+impl ChunkBuffers {
+    fn new(device: &wgpu::Device, pos: ChunkPos, mesh: &ChunkMesh) -> Self {
+        let layer = |label, vertices: &[Vertex]| {
+            (!vertices.is_empty()).then(|| MeshBuffer::new(device, label, vertices))
+        };
+        let min = pos.to_world_pos();
+
+        Self {
+            min,
+            max: min + Vec3::splat(CHUNK_SIZE as f32),
+            opaque: layer("Chunk Vertex Buffer", &mesh.opaque),
+            transparent: layer("Chunk Transparent Vertex Buffer", &mesh.transparent),
+            water: layer("Chunk Water Vertex Buffer", &mesh.water),
+        }
+    }
 }
 
 pub struct Renderer {
     pipeline: wgpu::RenderPipeline,
+    transparent_pipeline: wgpu::RenderPipeline,
+    water_pipeline: wgpu::RenderPipeline,
+    sky_pipeline: wgpu::RenderPipeline,
+    scene_depth_view_pipeline: wgpu::RenderPipeline,
+    shadow_depth_view_pipeline: wgpu::RenderPipeline,
+    shadow_depth_view_bind_group_layout: wgpu::BindGroupLayout,
     shadow_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
+    outline_pipeline: wgpu::RenderPipeline,
+    crosshair_pipeline: wgpu::RenderPipeline,
+    outline_buffer: wgpu::Buffer,
+    show_outline: bool,
+    crosshair_buffer: wgpu::Buffer,
+    chunk_meshes: HashMap<ChunkPos, ChunkBuffers>,
+    entity_mesh: Option<MeshBuffer>,
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
+    sky_buffer: wgpu::Buffer,
     depth_texture: Texture,
-    shadow_texture: Texture,
+    shadow_config: ShadowConfig,
+    shadow_map: ShadowMap,
     shadow_bind_group: wgpu::BindGroup,
-    shadow_uniforms: ShadowUniforms,
     shadow_buffer: wgpu::Buffer,
-    shadow_light_bind_group: wgpu::BindGroup,
-    light_instance: Light,
+    cascade_buffers: Vec<wgpu::Buffer>,
+    cascade_bind_groups: Vec<wgpu::BindGroup>,
+    light_direction: Vec3,
     light_buffer: wgpu::Buffer,
     light_bind_group: wgpu::BindGroup,
+    block_textures: Arc<BlockTextures>,
+    block_texture_bind_group: wgpu::BindGroup,
+    water_uniform: WaterUniform,
+    water_buffer: wgpu::Buffer,
+    water_bind_group_layout: wgpu::BindGroupLayout,
+    water_bind_group: wgpu::BindGroup,
 }
 
 impl Renderer {
     pub fn new(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
         camera: &Camera,
+        mut shadow_config: ShadowConfig,
     ) -> Self {
+        shadow_config.cascade_count = shadow_config.cascade_count.clamp(1, MAX_CASCADES as u32);
+
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(camera);
 
@@ -152,13 +179,27 @@ impl Renderer {
         let light_bind_group_layout = Self::create_light_bind_group_layout(device);
         let shadow_bind_group_layout = Self::create_shadow_bind_group_layout(device);
         let shadow_light_bind_group_layout = Self::create_shadow_light_bind_group_layout(device);
+        let block_texture_bind_group_layout = Self::create_block_texture_bind_group_layout(device);
+        let water_bind_group_layout = Self::create_water_bind_group_layout(device);
+
+        let sky_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sky Buffer"),
+            contents: bytemuck::cast_slice(&[<SkyUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
 
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sky_buffer.as_entire_binding(),
+                },
+            ],
             label: Some("camera_bind_group"),
         });
 
@@ -183,10 +224,8 @@ impl Renderer {
             label: Some("light_bind_group"),
         });
 
-        let shadow_texture = Texture::create_shadow_texture(device);
-        let shadow_uniforms = ShadowUniforms {
-            light_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
-        };
+        let shadow_map = ShadowMap::new(device, &shadow_config);
+        let shadow_uniforms = compute_cascades(camera, light_instance.direction, &shadow_config);
 
         let shadow_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Shadow Buffer"),
@@ -197,17 +236,49 @@ impl Renderer {
         let shadow_bind_group = Self::create_shadow_bind_group(
             device,
             &shadow_bind_group_layout,
-            &shadow_texture,
+            &shadow_map.texture,
             &shadow_buffer,
         );
 
-        let shadow_light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &shadow_light_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: shadow_buffer.as_entire_binding(),
-            }],
-            label: Some("shadow_light_bind_group"),
+        let cascade_buffers: Vec<_> = (0..shadow_config.cascade_count as usize)
+            .map(|cascade| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shadow Cascade Buffer"),
+                    contents: bytemuck::cast_slice(&[shadow_uniforms.light_view_proj(cascade)]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect();
+
+        let cascade_bind_groups = cascade_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &shadow_light_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("shadow_light_bind_group"),
+                })
+            })
+            .collect();
+
+        let block_textures = Arc::new(BlockTextures::load(BLOCK_TEXTURE_DIR));
+        let block_texture = Texture::create_block_texture_array(device, queue, &block_textures);
+        let block_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &block_texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&block_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&block_texture.sampler),
+                },
+            ],
+            label: Some("block_texture_bind_group"),
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -216,6 +287,7 @@ impl Renderer {
                 &camera_bind_group_layout,
                 &light_bind_group_layout,
                 &shadow_bind_group_layout,
+                &block_texture_bind_group_layout,
             ],
             push_constant_ranges: &[],
         });
@@ -225,6 +297,21 @@ impl Renderer {
             source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/shader.wgsl").into()),
         });
 
+        let water_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Water Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/water.wgsl").into()),
+        });
+
+        let sky_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/sky.wgsl").into()),
+        });
+
+        let depth_view_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth View Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/depth_view.wgsl").into()),
+        });
+
         let shadow_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shadow Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/shadow.wgsl").into()),
@@ -234,8 +321,77 @@ impl Renderer {
             device,
             &pipeline_layout,
             &shader,
-            config.format,
+            format,
             Vertex::desc(),
+            false,
+        );
+
+        let transparent_pipeline = Self::create_render_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            format,
+            Vertex::desc(),
+            true,
+        );
+
+        let water_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Water Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &light_bind_group_layout,
+                    &water_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let water_pipeline = Self::create_water_pipeline(
+            device,
+            &water_pipeline_layout,
+            &water_shader,
+            format,
+            Vertex::desc(),
+        );
+
+        let sky_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let sky_pipeline =
+            Self::create_sky_pipeline(device, &sky_pipeline_layout, &sky_shader, format);
+
+        // A uniform plus an unfilterable depth texture, same shape as the water group.
+        let scene_depth_view_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Scene Depth View Pipeline Layout"),
+                bind_group_layouts: &[&water_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let scene_depth_view_pipeline = Self::create_depth_view_pipeline(
+            device,
+            &scene_depth_view_pipeline_layout,
+            &depth_view_shader,
+            "fs_scene",
+        );
+
+        let shadow_depth_view_bind_group_layout =
+            Self::create_shadow_depth_view_bind_group_layout(device);
+        let shadow_depth_view_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shadow Depth View Pipeline Layout"),
+                bind_group_layouts: &[&shadow_depth_view_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shadow_depth_view_pipeline = Self::create_depth_view_pipeline(
+            device,
+            &shadow_depth_view_pipeline_layout,
+            &depth_view_shader,
+            "fs_shadow",
         );
 
         let shadow_pipeline = Self::create_shadow_pipeline(
@@ -245,46 +401,157 @@ impl Renderer {
             &shadow_light_bind_group_layout,
         );
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&Vec::<Vertex>::new()),
-            usage: wgpu::BufferUsages::VERTEX,
+        let lines_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Lines Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/lines.wgsl").into()),
         });
 
-        let depth_texture = Texture::create_depth_texture(device, config);
+        let lines_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Lines Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // Depth tested but not written, so the outline hides behind terrain
+        // without occluding anything drawn after it.
+        let outline_pipeline = Self::create_line_pipeline(
+            device,
+            &lines_pipeline_layout,
+            &lines_shader,
+            format,
+            "vs_world",
+            wgpu::CompareFunction::LessEqual,
+            wgpu::BlendState::REPLACE,
+        );
+
+        // Inverts the color underneath so the crosshair reads on any background.
+        let crosshair_pipeline = Self::create_line_pipeline(
+            device,
+            &lines_pipeline_layout,
+            &lines_shader,
+            format,
+            "vs_screen",
+            wgpu::CompareFunction::Always,
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::OneMinusDst,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        );
+
+        let outline_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Outline Buffer"),
+            size: std::mem::size_of::<[LineVertex; 24]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let crosshair_buffer = Self::create_crosshair_buffer(device, width, height);
+
+        let depth_texture = Texture::create_depth_texture(device, width, height);
+
+        let water_uniform = WaterUniform {
+            time: 0.0,
+            znear: camera.znear,
+            zfar: camera.zfar,
+            _padding: 0.0,
+        };
+
+        let water_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Water Buffer"),
+            contents: bytemuck::cast_slice(&[water_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let water_bind_group = Self::create_water_bind_group(
+            device,
+            &water_bind_group_layout,
+            &water_buffer,
+            &depth_texture,
+        );
 
         Self {
             pipeline,
+            transparent_pipeline,
+            water_pipeline,
+            sky_pipeline,
+            scene_depth_view_pipeline,
+            shadow_depth_view_pipeline,
+            shadow_depth_view_bind_group_layout,
             shadow_pipeline,
-            vertex_buffer,
-            num_vertices: 0,
+            outline_pipeline,
+            crosshair_pipeline,
+            outline_buffer,
+            show_outline: false,
+            crosshair_buffer,
+            chunk_meshes: HashMap::new(),
+            entity_mesh: None,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
+            sky_buffer,
             depth_texture,
-            shadow_texture,
+            shadow_config,
+            shadow_map,
             shadow_bind_group,
-            shadow_uniforms,
             shadow_buffer,
-            shadow_light_bind_group,
-            light_instance,
+            cascade_buffers,
+            cascade_bind_groups,
+            light_direction: light_instance.direction,
             light_buffer,
             light_bind_group,
+            block_textures,
+            block_texture_bind_group,
+            water_uniform,
+            water_buffer,
+            water_bind_group_layout,
+            water_bind_group,
         }
     }
 
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.depth_texture = Texture::create_depth_texture(device, width, height);
+        self.water_bind_group = Self::create_water_bind_group(
+            device,
+            &self.water_bind_group_layout,
+            &self.water_buffer,
+            &self.depth_texture,
+        );
+        self.crosshair_buffer = Self::create_crosshair_buffer(device, width, height);
+    }
+
+    pub fn block_textures(&self) -> &Arc<BlockTextures> {
+        &self.block_textures
+    }
+
     fn create_camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
             label: Some("camera_bind_group_layout"),
         })
     }
@@ -313,7 +580,7 @@ impl Renderer {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Depth,
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
                         multisampled: false,
                     },
                     count: None,
@@ -365,6 +632,82 @@ impl Renderer {
         })
     }
 
+    fn create_block_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("block_texture_bind_group_layout"),
+        })
+    }
+
+    fn create_water_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // Bound as unfilterable float rather than depth: GL backends
+                    // can't textureLoad from depth textures.
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("water_bind_group_layout"),
+        })
+    }
+
+    fn create_water_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        water_buffer: &wgpu::Buffer,
+        depth_texture: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: water_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+                },
+            ],
+            label: Some("water_bind_group"),
+        })
+    }
+
     fn create_shadow_light_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[wgpu::BindGroupLayoutEntry {
@@ -387,9 +730,71 @@ impl Renderer {
         shader: &wgpu::ShaderModule,
         format: wgpu::TextureFormat,
         vertex_layout: wgpu::VertexBufferLayout<'static>,
+        transparent: bool,
+    ) -> wgpu::RenderPipeline {
+        let (label, entry_point, blend, cull_mode) = if transparent {
+            (
+                "Transparent Render Pipeline",
+                "fs_transparent",
+                wgpu::BlendState::ALPHA_BLENDING,
+                None,
+            )
+        } else {
+            (
+                "Render Pipeline",
+                "fs_main",
+                wgpu::BlendState::REPLACE,
+                Some(wgpu::Face::Back),
+            )
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: !transparent,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_water_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        vertex_layout: wgpu::VertexBufferLayout<'static>,
     ) -> wgpu::RenderPipeline {
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
+            label: Some("Water Pipeline"),
             layout: Some(layout),
             vertex: wgpu::VertexState {
                 module: shader,
@@ -401,7 +806,7 @@ impl Renderer {
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -409,14 +814,14 @@ impl Renderer {
                 topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
+                cull_mode: None,
                 unclipped_depth: false,
                 polygon_mode: wgpu::PolygonMode::Fill,
                 conservative: false,
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
+                depth_write_enabled: false,
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
@@ -426,6 +831,165 @@ impl Renderer {
         })
     }
 
+    fn create_sky_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sky Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_line_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        vertex_entry_point: &str,
+        depth_compare: wgpu::CompareFunction,
+        blend: wgpu::BlendState,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Line Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: vertex_entry_point,
+                buffers: &[LineVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_crosshair_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Crosshair Buffer"),
+            contents: bytemuck::cast_slice(&crosshair_lines(width, height)),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    }
+
+    // GL can't sample a single-layer view of an array texture, so cascades are
+    // read through the whole shadow map array instead.
+    fn create_shadow_depth_view_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadow_depth_view_bind_group_layout"),
+        })
+    }
+
+    fn create_depth_view_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth View Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: fragment_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: DEPTH_VIEW_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
     fn create_shadow_pipeline(
         device: &wgpu::Device,
         shader: &wgpu::ShaderModule,
@@ -461,26 +1025,17 @@ impl Renderer {
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::LessEqual,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
             }),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
         })
     }
 
-    pub fn update_shadow_uniforms(&mut self, light_direction: Vec3) {
-        let light_projection = Mat4::orthographic_rh(
-            -50.0,
-            50.0,
-            -50.0,
-            50.0,
-            -50.0,
-            50.0
-        );
-        let light_view = Mat4::look_to_rh(Vec3::ZERO, light_direction, Vec3::Y);
-        self.shadow_uniforms.light_view_proj = (light_projection * light_view).to_cols_array_2d();
-    }
-
     pub fn render(
         &mut self,
         view: &wgpu::TextureView,
@@ -492,19 +1047,25 @@ impl Renderer {
             label: Some("Render Encoder"),
         });
 
-        self.update_shadow_uniforms(self.light_instance.direction);
+        let shadow_uniforms = compute_cascades(camera, self.light_direction, &self.shadow_config);
         queue.write_buffer(
             &self.shadow_buffer,
             0,
-            bytemuck::cast_slice(&[self.shadow_uniforms]),
+            bytemuck::cast_slice(&[shadow_uniforms]),
         );
 
-        {
+        for (cascade, cascade_view) in self.shadow_map.cascade_views.iter().enumerate() {
+            queue.write_buffer(
+                &self.cascade_buffers[cascade],
+                0,
+                bytemuck::cast_slice(&[shadow_uniforms.light_view_proj(cascade)]),
+            );
+
             let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Shadow Pass"),
                 color_attachments: &[],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.shadow_texture.view,
+                    view: cascade_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: true,
@@ -514,11 +1075,32 @@ impl Renderer {
             });
 
             shadow_pass.set_pipeline(&self.shadow_pipeline);
-            shadow_pass.set_bind_group(0, &self.shadow_light_bind_group, &[]);
-            shadow_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            shadow_pass.draw(0..self.num_vertices, 0..1);
+            shadow_pass.set_bind_group(0, &self.cascade_bind_groups[cascade], &[]);
+            // Every chunk can cast a shadow into view, not just the visible ones.
+            for mesh in self
+                .chunk_meshes
+                .values()
+                .filter_map(|chunk| chunk.opaque.as_ref())
+            {
+                mesh.draw(&mut shadow_pass);
+            }
+            if let Some(mesh) = &self.entity_mesh {
+                mesh.draw(&mut shadow_pass);
+            }
         }
 
+        let frustum = Frustum::from_matrix(camera.build_view_projection_matrix());
+        let mut visible: Vec<&ChunkBuffers> = self
+            .chunk_meshes
+            .values()
+            .filter(|chunk| frustum.is_box_visible(chunk.min, chunk.max))
+            .collect();
+        // Back to front, so translucent chunks blend over the ones behind them.
+        let distance = |chunk: &ChunkBuffers| {
+            ((chunk.min + chunk.max) * 0.5).distance_squared(camera.position)
+        };
+        visible.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
         self.camera_uniform.update_view_proj(camera);
         queue.write_buffer(
             &self.camera_buffer,
@@ -526,6 +1108,14 @@ impl Renderer {
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
 
+        self.water_uniform.znear = camera.znear;
+        self.water_uniform.zfar = camera.zfar;
+        queue.write_buffer(
+            &self.water_buffer,
+            0,
+            bytemuck::cast_slice(&[self.water_uniform]),
+        );
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -533,12 +1123,7 @@ impl Renderer {
                     view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: true,
                     },
                 })],
@@ -552,36 +1137,246 @@ impl Renderer {
                 }),
             });
 
-            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_pipeline(&self.sky_pipeline);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(1, &self.light_bind_group, &[]);
             render_pass.set_bind_group(2, &self.shadow_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
+            render_pass.set_bind_group(3, &self.block_texture_bind_group, &[]);
+            for mesh in visible.iter().filter_map(|chunk| chunk.opaque.as_ref()) {
+                mesh.draw(&mut render_pass);
+            }
+            if let Some(mesh) = &self.entity_mesh {
+                mesh.draw(&mut render_pass);
+            }
+        }
+
+        {
+            let mut transparent_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Transparent Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: None,
+                    stencil_ops: None,
+                }),
+            });
+
+            transparent_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            transparent_pass.set_bind_group(1, &self.light_bind_group, &[]);
+            transparent_pass.set_bind_group(3, &self.block_texture_bind_group, &[]);
+            // Water and the other translucent faces go chunk by chunk in the
+            // same back to front order, so each blends over everything behind
+            // it. Faces within one chunk aren't sorted.
+            for chunk in &visible {
+                if let Some(mesh) = &chunk.transparent {
+                    transparent_pass.set_pipeline(&self.transparent_pipeline);
+                    transparent_pass.set_bind_group(2, &self.shadow_bind_group, &[]);
+                    mesh.draw(&mut transparent_pass);
+                }
+                if let Some(mesh) = &chunk.water {
+                    transparent_pass.set_pipeline(&self.water_pipeline);
+                    transparent_pass.set_bind_group(2, &self.water_bind_group, &[]);
+                    mesh.draw(&mut transparent_pass);
+                }
+            }
         }
 
         queue.submit(std::iter::once(encoder.finish()));
         Ok(())
     }
 
-    pub fn update_light_buffer(
+    /// Outlines `bounds` in the next `render_overlay`, or nothing if `None`.
+    pub fn set_outline(&mut self, queue: &wgpu::Queue, bounds: Option<Aabb>) {
+        self.show_outline = bounds.is_some();
+        if let Some(bounds) = bounds {
+            queue.write_buffer(
+                &self.outline_buffer,
+                0,
+                bytemuck::cast_slice(&box_lines(&bounds, OUTLINE_COLOR)),
+            );
+        }
+    }
+
+    /// Draws the block outline and crosshair over a frame `render` just drew
+    /// into `view`, depth testing the outline against that frame's depth.
+    pub fn render_overlay(
+        &self,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Overlay Encoder"),
+        });
+
+        {
+            let mut overlay_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: None,
+                    stencil_ops: None,
+                }),
+            });
+
+            overlay_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            if self.show_outline {
+                overlay_pass.set_pipeline(&self.outline_pipeline);
+                overlay_pass.set_vertex_buffer(0, self.outline_buffer.slice(..));
+                overlay_pass.draw(0..24, 0..1);
+            }
+
+            overlay_pass.set_pipeline(&self.crosshair_pipeline);
+            overlay_pass.set_vertex_buffer(0, self.crosshair_buffer.slice(..));
+            overlay_pass.draw(0..4, 0..1);
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Size of the image `render_depth_view` produces for `source`.
+    pub fn depth_view_size(&self, source: DepthSource) -> (u32, u32) {
+        match source {
+            DepthSource::Scene => {
+                let texture = &self.depth_texture.texture;
+                (texture.width(), texture.height())
+            }
+            DepthSource::ShadowCascade(_) => {
+                (self.shadow_config.resolution, self.shadow_config.resolution)
+            }
+        }
+    }
+
+    /// Draws the last frame's scene depth or one shadow cascade as grayscale
+    /// into `target`, which must be `DEPTH_VIEW_FORMAT` and
+    /// `depth_view_size(source)` big. Depth textures can't be copied to
+    /// buffers on every backend, so they're drawn rather than read back.
+    pub fn render_depth_view(
         &self,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
-        light_uniform: &crate::engine::light::LightUniform,
+        target: &wgpu::TextureView,
+        source: DepthSource,
     ) {
+        let (pipeline, layout, binding, view, uniform) = match source {
+            DepthSource::Scene => (
+                &self.scene_depth_view_pipeline,
+                &self.water_bind_group_layout,
+                1,
+                &self.depth_texture.view,
+                DepthViewUniform {
+                    znear: self.water_uniform.znear,
+                    zfar: self.water_uniform.zfar,
+                    layer: 0,
+                    _padding: 0.0,
+                },
+            ),
+            DepthSource::ShadowCascade(cascade) => (
+                &self.shadow_depth_view_pipeline,
+                &self.shadow_depth_view_bind_group_layout,
+                2,
+                &self.shadow_map.texture.view,
+                DepthViewUniform {
+                    znear: 0.0,
+                    zfar: 1.0,
+                    layer: cascade.min(self.shadow_config.cascade_count - 1) as i32,
+                    _padding: 0.0,
+                },
+            ),
+        };
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth View Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+            ],
+            label: Some("depth_view_bind_group"),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Depth View Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth View Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    pub fn update_light_buffer(&mut self, queue: &wgpu::Queue, light: &Light) {
+        self.light_direction = light.direction;
         queue.write_buffer(
             &self.light_buffer,
             0,
-            bytemuck::cast_slice(&[*light_uniform]),
+            bytemuck::cast_slice(&[light.uniform]),
         );
     }
 
-    pub fn update_vertices(&mut self, device: &wgpu::Device, vertices: &[Vertex]) {
-        self.vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        self.num_vertices = vertices.len() as u32;
+    pub fn update_sky_buffer(&self, queue: &wgpu::Queue, sky_uniform: &SkyUniform) {
+        queue.write_buffer(&self.sky_buffer, 0, bytemuck::cast_slice(&[*sky_uniform]));
+    }
+
+    pub fn update_time(&mut self, time: f32) {
+        self.water_uniform.time = time;
+    }
+
+    /// Replaces the GPU copy of the chunk at `pos` with `mesh`.
+    pub fn upload_chunk_mesh(&mut self, device: &wgpu::Device, pos: ChunkPos, mesh: &ChunkMesh) {
+        self.chunk_meshes
+            .insert(pos, ChunkBuffers::new(device, pos, mesh));
+    }
+
+    /// Replaces the entity models drawn each frame; `vertices` are in world
+    /// space and drawn with the opaque terrain.
+    pub fn upload_entity_mesh(&mut self, device: &wgpu::Device, vertices: &[Vertex]) {
+        self.entity_mesh = (!vertices.is_empty())
+            .then(|| MeshBuffer::new(device, "Entity Vertex Buffer", vertices));
+    }
+
+    pub fn remove_chunk_mesh(&mut self, pos: ChunkPos) {
+        self.chunk_meshes.remove(&pos);
     }
 }
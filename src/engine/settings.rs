@@ -0,0 +1,74 @@
+use crate::engine::timestep::DEFAULT_TICK_RATE;
+use crate::world::chunk_cache::DEFAULT_CACHE_BUDGET;
+use crate::world::chunk_manager::ViewDistance;
+use crate::world::chunk_worker::DEFAULT_DISPATCH_BUDGET;
+use crate::world::clock::DEFAULT_DAY_LENGTH;
+
+/// Startup options, read from the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub tick_rate: f32,
+    pub day_length: f32,
+    pub view_distance: ViewDistance,
+    /// Chunk tasks handed to the worker threads per frame.
+    pub chunk_dispatch_budget: usize,
+    /// Bytes of unloaded chunks kept in memory.
+    pub chunk_cache_budget: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tick_rate: DEFAULT_TICK_RATE,
+            day_length: DEFAULT_DAY_LENGTH,
+            view_distance: ViewDistance::default(),
+            chunk_dispatch_budget: DEFAULT_DISPATCH_BUDGET,
+            chunk_cache_budget: DEFAULT_CACHE_BUDGET,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `--tick-rate <hz>`, `--day-length <seconds>`, `--view-distance
+    /// <chunks>`, `--vertical-view-distance <chunks>`, `--unload-margin
+    /// <chunks>`, `--chunk-budget <tasks>` and `--chunk-cache-mb <MiB>`;
+    /// anything missing or unparsable keeps its default.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let mut settings = Self::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let value = args.peek().cloned().unwrap_or_default();
+            match arg.as_str() {
+                "--tick-rate" => settings.tick_rate = value.parse().unwrap_or(settings.tick_rate),
+                "--day-length" => {
+                    settings.day_length = value.parse().unwrap_or(settings.day_length)
+                }
+                "--view-distance" => {
+                    let view_distance = &mut settings.view_distance;
+                    view_distance.horizontal = value.parse().unwrap_or(view_distance.horizontal)
+                }
+                "--vertical-view-distance" => {
+                    let view_distance = &mut settings.view_distance;
+                    view_distance.vertical = value.parse().unwrap_or(view_distance.vertical)
+                }
+                "--unload-margin" => {
+                    let view_distance = &mut settings.view_distance;
+                    view_distance.unload_margin =
+                        value.parse().unwrap_or(view_distance.unload_margin)
+                }
+                "--chunk-budget" => {
+                    settings.chunk_dispatch_budget =
+                        value.parse().unwrap_or(settings.chunk_dispatch_budget)
+                }
+                "--chunk-cache-mb" => {
+                    if let Ok(megabytes) = value.parse::<usize>() {
+                        settings.chunk_cache_budget = megabytes * 1024 * 1024;
+                    }
+                }
+                _ => continue,
+            }
+            args.next();
+        }
+        settings
+    }
+}
@@ -0,0 +1,198 @@
+use crate::engine::camera::Camera;
+use crate::engine::texture::Texture;
+use glam::{Mat4, Vec3, Vec4};
+
+pub const MAX_CASCADES: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowConfig {
+    pub cascade_count: u32,
+    pub resolution: u32,
+    pub max_distance: f32,
+    /// Blend between uniform (0.0) and logarithmic (1.0) cascade splits.
+    pub split_lambda: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            cascade_count: 4,
+            resolution: 2048,
+            max_distance: 160.0,
+            split_lambda: 0.6,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniforms {
+    light_view_proj: [[[f32; 4]; 4]; MAX_CASCADES],
+    splits: [f32; 4],
+    params: [f32; 4],
+}
+
+impl ShadowUniforms {
+    pub fn light_view_proj(&self, cascade: usize) -> [[f32; 4]; 4] {
+        self.light_view_proj[cascade]
+    }
+}
+
+pub struct ShadowMap {
+    pub texture: Texture,
+    pub cascade_views: Vec<wgpu::TextureView>,
+}
+
+impl ShadowMap {
+    pub fn new(device: &wgpu::Device, config: &ShadowConfig) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Texture"),
+            size: wgpu::Extent3d {
+                width: config.resolution,
+                height: config.resolution,
+                depth_or_array_layers: config.cascade_count,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let cascade_views = (0..config.cascade_count)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture: Texture {
+                texture,
+                view,
+                sampler,
+            },
+            cascade_views,
+        }
+    }
+}
+
+fn cascade_splits(camera: &Camera, config: &ShadowConfig) -> Vec<f32> {
+    let near = camera.znear;
+    let far = config.max_distance.min(camera.zfar);
+    let count = config.cascade_count as usize;
+
+    (1..=count)
+        .map(|i| {
+            let p = i as f32 / count as f32;
+            let log = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            uniform + (log - uniform) * config.split_lambda
+        })
+        .collect()
+}
+
+/// Fits one orthographic light projection around each slice of the camera
+/// frustum. Each slice is wrapped in a bounding sphere so the projection size
+/// doesn't change as the camera rotates, and its center is snapped to whole
+/// shadow texels so shadows don't shimmer as the camera moves.
+pub fn compute_cascades(
+    camera: &Camera,
+    light_direction: Vec3,
+    config: &ShadowConfig,
+) -> ShadowUniforms {
+    let mut uniforms = ShadowUniforms {
+        light_view_proj: [Mat4::IDENTITY.to_cols_array_2d(); MAX_CASCADES],
+        splits: [f32::MAX; 4],
+        params: [
+            config.cascade_count as f32,
+            1.0 / config.resolution as f32,
+            0.0,
+            0.0,
+        ],
+    };
+
+    let light_direction = light_direction.normalize();
+    let up = if light_direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let light_view = Mat4::look_to_rh(Vec3::ZERO, light_direction, up);
+    let camera_view = camera.build_view_matrix();
+
+    let mut slice_near = camera.znear;
+    for (cascade, slice_far) in cascade_splits(camera, config).into_iter().enumerate() {
+        let projection = Mat4::perspective_rh(
+            camera.fovy.to_radians(),
+            camera.aspect,
+            slice_near,
+            slice_far,
+        );
+        let inverse = (projection * camera_view).inverse();
+
+        let mut corners = [Vec3::ZERO; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let ndc = Vec4::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+                1.0,
+            );
+            let world = inverse * ndc;
+            *corner = world.truncate() / world.w;
+        }
+
+        let center = corners.iter().copied().sum::<Vec3>() / 8.0;
+        let radius = corners
+            .iter()
+            .map(|corner| corner.distance(center))
+            .fold(0.0, f32::max)
+            .ceil();
+
+        let texel_size = radius * 2.0 / config.resolution as f32;
+        let mut light_center = light_view.transform_point3(center);
+        light_center.x = (light_center.x / texel_size).floor() * texel_size;
+        light_center.y = (light_center.y / texel_size).floor() * texel_size;
+
+        // Casters between the slice and the sun still need to land in the map.
+        let caster_margin = config.max_distance;
+        let light_projection = Mat4::orthographic_rh(
+            light_center.x - radius,
+            light_center.x + radius,
+            light_center.y - radius,
+            light_center.y + radius,
+            -light_center.z - radius - caster_margin,
+            -light_center.z + radius,
+        );
+
+        uniforms.light_view_proj[cascade] = (light_projection * light_view).to_cols_array_2d();
+        uniforms.splits[cascade] = slice_far;
+        slice_near = slice_far;
+    }
+
+    uniforms
+}
@@ -0,0 +1,76 @@
+use crate::engine::camera::Camera;
+use crate::utils::math::smoothstep;
+use crate::world::clock;
+use glam::{Mat4, Vec3};
+
+const DAY_ZENITH: Vec3 = Vec3::new(0.18, 0.42, 0.85);
+const DAY_HORIZON: Vec3 = Vec3::new(0.62, 0.78, 0.95);
+const SUNSET_ZENITH: Vec3 = Vec3::new(0.25, 0.25, 0.5);
+const SUNSET_HORIZON: Vec3 = Vec3::new(0.95, 0.5, 0.25);
+const NIGHT_ZENITH: Vec3 = Vec3::new(0.005, 0.008, 0.025);
+const NIGHT_HORIZON: Vec3 = Vec3::new(0.03, 0.04, 0.08);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    sun_direction: [f32; 4],
+    zenith_color: [f32; 4],
+    horizon_color: [f32; 4],
+    fog: [f32; 4],
+}
+
+pub struct Sky {
+    pub sun_direction: Vec3,
+    pub fog_start: f32,
+    pub fog_end: f32,
+    pub uniform: SkyUniform,
+}
+
+impl Sky {
+    pub fn new(fog_start: f32, fog_end: f32) -> Self {
+        Self {
+            sun_direction: Vec3::Y,
+            fog_start,
+            fog_end,
+            uniform: SkyUniform {
+                inv_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+                sun_direction: [0.0, 1.0, 0.0, 0.0],
+                zenith_color: [DAY_ZENITH.x, DAY_ZENITH.y, DAY_ZENITH.z, 1.0],
+                horizon_color: [DAY_HORIZON.x, DAY_HORIZON.y, DAY_HORIZON.z, 1.0],
+                fog: [fog_start, fog_end, 0.0, 0.0],
+            },
+        }
+    }
+
+    pub fn daylight(&self) -> f32 {
+        clock::daylight(self.sun_direction.y)
+    }
+
+    /// Peaks while the sun is crossing the horizon.
+    pub fn twilight(&self) -> f32 {
+        1.0 - smoothstep(0.0, 0.35, self.sun_direction.y.abs())
+    }
+
+    pub fn update(&mut self, camera: &Camera) {
+        let daylight = self.daylight();
+        let twilight = self.twilight();
+
+        let zenith = NIGHT_ZENITH
+            .lerp(DAY_ZENITH, daylight)
+            .lerp(SUNSET_ZENITH, twilight * 0.5);
+        let horizon = NIGHT_HORIZON
+            .lerp(DAY_HORIZON, daylight)
+            .lerp(SUNSET_HORIZON, twilight * 0.7);
+
+        let sun = self.sun_direction.normalize();
+        self.uniform.inv_view_proj = camera
+            .build_view_projection_matrix()
+            .inverse()
+            .to_cols_array_2d();
+        self.uniform.sun_direction = [sun.x, sun.y, sun.z, twilight];
+        self.uniform.zenith_color = [zenith.x, zenith.y, zenith.z, 1.0];
+        self.uniform.horizon_color = [horizon.x, horizon.y, horizon.z, 1.0];
+        self.uniform.fog = [self.fog_start, self.fog_end, 1.0 - daylight, 0.0];
+    }
+}
@@ -1,17 +1,53 @@
 use super::{
     camera::{Camera, CameraController},
-    renderer::Renderer,
+    renderer::{DepthSource, Renderer},
 };
+use crate::engine::capture::{
+    capture_path, tile_transform, Capture, CaptureError, OffscreenTarget, DEPTH_VIEW_FORMAT,
+    SCREENSHOT_DIR,
+};
+use crate::engine::gpu::request_device;
 use crate::engine::light::Light;
+use crate::engine::settings::Settings;
+use crate::engine::shadow::ShadowConfig;
+use crate::engine::sky::Sky;
+use crate::engine::timestep::FixedTimestep;
+use crate::utils::aabb::Aabb;
 use crate::utils::frustum::Frustum;
-use crate::world::chunk::{Chunk, ChunkPos};
-use crate::world::chunk_manager::ChunkManager;
-use crate::world::chunk_worker::ChunkWorkerPool;
-use glam::Vec3;
-use parking_lot::Mutex;
-use std::collections::{HashMap, HashSet};
+use crate::world::access::World;
+use crate::world::block::{BlockType, PLACEABLE_BLOCKS};
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::chunk_cache::CacheStats;
+use crate::world::chunk_events::ChunkEvent;
+use crate::world::chunk_manager::{ChunkManager, ViewDistance};
+use crate::world::clock::{WorldClock, MIDNIGHT, NOON};
+use crate::world::entity::{mesh_entities, Entity, EntityKind};
+use crate::world::mob::Behavior;
+use crate::world::player::{Player, PLAYER_EYE_HEIGHT};
+use crate::world::raycast::{RaycastFilter, RaycastHit};
+use crossbeam::channel::Receiver;
+use glam::{IVec3, Mat4, Vec3};
+use std::path::PathBuf;
 use std::sync::Arc;
-use winit::{event::WindowEvent, window::Window};
+use std::time::Instant;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+const HIRES_TILES: u32 = 4;
+/// How far away, in blocks, the player can pick a block.
+const REACH: f32 = 6.0;
+/// Seconds before a dropped item can be picked up, so it's seen falling.
+const PICKUP_DELAY: f32 = 1.0;
+/// How far outside the player's box items are picked up from.
+const PICKUP_RANGE: f32 = 0.5;
+/// Blocks per second.
+const THROW_SPEED: f32 = 25.0;
+/// A right click that moves the cursor less than this many pixels places a
+/// block; anything more is a look drag.
+const CLICK_SLOP: f64 = 4.0;
 
 pub struct State {
     surface: wgpu::Surface,
@@ -21,18 +57,32 @@ pub struct State {
     pub size: winit::dpi::PhysicalSize<u32>,
     pub camera: Camera,
     camera_controller: CameraController,
+    player: Player,
+    /// Free camera with no collision instead of walking.
+    flying: bool,
     renderer: Renderer,
-    chunks: Arc<Mutex<HashMap<ChunkPos, Chunk>>>,
-    chunk_worker: ChunkWorkerPool,
-    chunk_manager: ChunkManager,
-    render_distance: i32,
-    last_chunk_pos: Option<ChunkPos>,
+    world: World,
+    /// The block the camera is looking at, if any is within reach.
+    target: Option<RaycastHit>,
+    /// Index into `PLACEABLE_BLOCKS`.
+    selected_block: usize,
+    /// What the next mob spawned will do.
+    next_behavior: Behavior,
+    cursor_position: PhysicalPosition<f64>,
+    right_press_position: Option<PhysicalPosition<f64>>,
+    chunk_events: Receiver<ChunkEvent>,
     time: f32,
+    clock: WorldClock,
     light: Light,
+    sky: Sky,
+    pending_capture: Option<Capture>,
+    timestep: FixedTimestep,
+    previous_position: Vec3,
+    current_position: Vec3,
 }
 
 impl State {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, settings: &Settings) -> Self {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
@@ -40,24 +90,7 @@ impl State {
         });
 
         let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
+        let (adapter, device, queue) = request_device(&instance, Some(&surface), false)
             .await
             .unwrap();
 
@@ -80,16 +113,25 @@ impl State {
         };
         surface.configure(&device, &config);
 
-        let camera = Camera::new(
-            Vec3::new(8.0, 100.0, 8.0),
-            size.width as f32 / size.height as f32,
+        let position = Vec3::new(8.0, 100.0, 8.0);
+        let camera = Camera::new(position, size.width as f32 / size.height as f32);
+        let camera_controller = CameraController::new(30.0);
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            config.format,
+            config.width,
+            config.height,
+            &camera,
+            ShadowConfig::default(),
         );
-        let camera_controller = CameraController::new(0.5);
-        let renderer = Renderer::new(&device, &config, &camera);
 
-        let chunks = Arc::new(Mutex::new(HashMap::new()));
-        let chunk_worker = ChunkWorkerPool::new(Arc::clone(&chunks));
-        let chunk_manager = ChunkManager::new();
+        let mut chunk_manager = ChunkManager::new(
+            settings.view_distance,
+            settings.chunk_dispatch_budget,
+            settings.chunk_cache_budget,
+            Arc::clone(renderer.block_textures()),
+        );
 
         let mut state = Self {
             surface,
@@ -99,68 +141,331 @@ impl State {
             size,
             camera,
             camera_controller,
+            player: Player::new(position - Vec3::Y * PLAYER_EYE_HEIGHT),
+            flying: true,
             renderer,
-            chunks,
-            chunk_worker,
-            chunk_manager,
-            render_distance: 8,
-            last_chunk_pos: None,
+            chunk_events: chunk_manager.subscribe(),
+            world: World::new(chunk_manager),
+            target: None,
+            selected_block: 0,
+            next_behavior: Behavior::Wander,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            right_press_position: None,
             time: 0.0,
+            clock: WorldClock::new(settings.day_length),
             light: Light::new(
                 Vec3::new(0.0, 100.0, 0.0),
                 Vec3::new(1.0, 1.0, 1.0),
                 Vec3::new(-0.5, -1.0, -0.3),
             ),
+            sky: Sky::new(0.0, 0.0),
+            pending_capture: None,
+            timestep: FixedTimestep::new(settings.tick_rate),
+            previous_position: position,
+            current_position: position,
         };
 
-        state.update_chunks();
+        state.apply_view_distance();
+        let frustum = Frustum::from_matrix(state.camera.build_view_projection_matrix());
+        state
+            .world
+            .chunks_mut()
+            .update(state.camera.position, &frustum);
         state
     }
 
+    pub fn view_distance(&self) -> ViewDistance {
+        self.world.chunks().view_distance()
+    }
+
+    fn adjust_view_distance(&mut self, horizontal: i32, vertical: i32) {
+        let mut view_distance = self.view_distance();
+        view_distance.horizontal += horizontal;
+        view_distance.vertical += vertical;
+        self.world.chunks_mut().set_view_distance(view_distance);
+        self.apply_view_distance();
+    }
+
+    /// Fades fog in over the outer part of the view distance and pulls the far
+    /// plane in just past it.
+    fn apply_view_distance(&mut self) {
+        let distance = self.view_distance().world_distance();
+        self.sky.fog_start = distance * 0.6;
+        self.sky.fog_end = distance;
+        self.camera.zfar = distance + CHUNK_SIZE as f32;
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.renderer
+                .resize(&self.device, self.config.width, self.config.height);
             self.camera.aspect = self.config.width as f32 / self.config.height as f32;
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
+        // Right button events still reach the camera controller for looking
+        // around; a click without a drag places a block on release.
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor_position = *position,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.break_block();
+                return true;
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => match state {
+                ElementState::Pressed => self.right_press_position = Some(self.cursor_position),
+                ElementState::Released => {
+                    let cursor = self.cursor_position;
+                    let clicked = self.right_press_position.take().is_some_and(|start| {
+                        (cursor.x - start.x).hypot(cursor.y - start.y) < CLICK_SLOP
+                    });
+                    if clicked {
+                        self.place_block();
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let capture = match keycode {
+                VirtualKeyCode::F2 => Some(Capture::Color),
+                VirtualKeyCode::F3 => Some(Capture::Depth),
+                VirtualKeyCode::F4 => Some(Capture::ShadowMap { cascade: 0 }),
+                VirtualKeyCode::F5 => Some(Capture::Tiled { tiles: HIRES_TILES }),
+                _ => None,
+            };
+            if capture.is_some() {
+                self.pending_capture = capture;
+                return true;
+            }
+
+            let hour = self.clock.day_length() / 24.0;
+            match keycode {
+                VirtualKeyCode::P => self.clock.set_paused(!self.clock.is_paused()),
+                VirtualKeyCode::Period => self.clock.fast_forward(hour),
+                VirtualKeyCode::Equals => self.clock.set_speed(self.clock.speed() * 2.0),
+                VirtualKeyCode::Minus => self.clock.set_speed(self.clock.speed() * 0.5),
+                VirtualKeyCode::N => self.clock.set_time_of_day(NOON),
+                VirtualKeyCode::M => self.clock.set_time_of_day(MIDNIGHT),
+                VirtualKeyCode::F => self.toggle_flying(),
+                VirtualKeyCode::G => self.spawn_mob(),
+                VirtualKeyCode::Q => self.throw_projectile(),
+                VirtualKeyCode::I => self.ignite(),
+                VirtualKeyCode::RBracket => self.adjust_view_distance(1, 0),
+                VirtualKeyCode::LBracket => self.adjust_view_distance(-1, 0),
+                VirtualKeyCode::PageUp => self.adjust_view_distance(0, 1),
+                VirtualKeyCode::PageDown => self.adjust_view_distance(0, -1),
+                VirtualKeyCode::Key1 => self.selected_block = 0,
+                VirtualKeyCode::Key2 => self.selected_block = 1,
+                VirtualKeyCode::Key3 => self.selected_block = 2,
+                VirtualKeyCode::Key4 => self.selected_block = 3,
+                VirtualKeyCode::Key5 => self.selected_block = 4,
+                VirtualKeyCode::Key6 => self.selected_block = 5,
+                VirtualKeyCode::Key7 => self.selected_block = 6,
+                VirtualKeyCode::Key8 => self.selected_block = 7,
+                VirtualKeyCode::Key9 => self.selected_block = 8,
+                VirtualKeyCode::Key0 => self.selected_block = 9,
+                _ => {
+                    return self
+                        .camera_controller
+                        .process_events(event, &mut self.camera)
+                }
+            }
+            return true;
+        }
+
         self.camera_controller
             .process_events(event, &mut self.camera)
     }
 
+    pub fn clock(&self) -> &WorldClock {
+        &self.clock
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        self.timestep.tick_rate()
+    }
+
+    pub fn target(&self) -> Option<RaycastHit> {
+        self.target
+    }
+
+    pub fn selected_block(&self) -> BlockType {
+        PLACEABLE_BLOCKS[self.selected_block]
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.flying
+    }
+
+    /// Switches between the free camera and walking. Walking starts from
+    /// wherever the camera is, at rest.
+    fn toggle_flying(&mut self) {
+        self.flying = !self.flying;
+        self.player.set_eye_position(self.current_position);
+    }
+
+    fn break_block(&mut self) {
+        let Some(hit) = self.target.filter(|hit| hit.block.is_breakable()) else {
+            return;
+        };
+        self.world.set_block(hit.position, BlockType::Air);
+        // Putting out a fire leaves nothing to pick up.
+        if !matches!(hit.block, BlockType::Fire(_)) {
+            self.world.spawn_entity(Entity::new(
+                EntityKind::Item(hit.block),
+                hit.position.as_vec3() + Vec3::new(0.5, 0.25, 0.5),
+                Vec3::Y * 4.0,
+            ));
+        }
+        self.target = None;
+    }
+
+    /// Puts a mob on the targeted face. Each one spawned behaves differently
+    /// from the last.
+    fn spawn_mob(&mut self) {
+        let Some(hit) = self.target.filter(|hit| hit.normal != IVec3::ZERO) else {
+            return;
+        };
+        let position = (hit.position + hit.normal).as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+        let mob = Entity::new(EntityKind::Mob(self.next_behavior), position, Vec3::ZERO);
+        if self.world.spawn_entity(mob) {
+            self.next_behavior = self.next_behavior.next();
+        }
+    }
+
+    fn throw_projectile(&mut self) {
+        let direction = self.camera.get_view_direction();
+        self.world.spawn_entity(Entity::new(
+            EntityKind::Projectile,
+            self.camera.position + direction * 0.5,
+            direction * THROW_SPEED,
+        ));
+    }
+
+    fn place_block(&mut self) {
+        self.put_block(self.selected_block());
+    }
+
+    /// Lights a fire against the targeted face.
+    fn ignite(&mut self) {
+        self.put_block(BlockType::Fire(0));
+    }
+
+    /// Puts `block` against the targeted face, unless the space is taken or
+    /// the player is standing in it.
+    fn put_block(&mut self, block: BlockType) {
+        let Some(hit) = self.target.filter(|hit| hit.normal != IVec3::ZERO) else {
+            return;
+        };
+        let position = hit.position + hit.normal;
+
+        let replaceable = self
+            .world
+            .get_block(position)
+            .is_some_and(|block| block.is_replaceable());
+        if !replaceable || self.player.bounds().intersects(&Aabb::from_block(position)) {
+            return;
+        }
+
+        self.world.set_block(position, block);
+        self.target = None;
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.world.chunks().cache_stats()
+    }
+
+    /// Writes every changed chunk to disk; called on exit.
+    pub fn save_world(&mut self) {
+        if let Err(e) = self.world.chunks_mut().save_all() {
+            eprintln!("Failed to save world: {}", e);
+        }
+    }
+
+    /// Runs however many fixed ticks have built up since the last frame, then
+    /// places the camera between the last two ticks for rendering.
     pub fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera);
-        self.chunk_manager
-            .update(self.camera.position, self.render_distance);
-
-        let current_chunk_pos = ChunkPos::from_world_pos(self.camera.position);
-        if self
-            .last_chunk_pos
-            .map_or(true, |pos| pos != current_chunk_pos)
-        {
-            self.last_chunk_pos = Some(current_chunk_pos);
-            self.update_chunks();
+        let ticks = self.timestep.update(Instant::now());
+        let dt = self.timestep.dt();
 
-            for _ in 0..4 {
-                self.chunk_worker.process_tasks();
-            }
-        } else {
-            self.chunk_worker.process_tasks();
+        self.camera.position = self.current_position;
+        for _ in 0..ticks {
+            self.previous_position = self.current_position;
+            self.tick(dt);
+            self.current_position = self.camera.position;
         }
+        self.camera.position = self
+            .previous_position
+            .lerp(self.current_position, self.timestep.alpha());
+
+        let frustum = Frustum::from_matrix(self.camera.build_view_projection_matrix());
+        self.world
+            .chunks_mut()
+            .update(self.camera.position, &frustum);
+        self.target = self.world.raycast(
+            self.camera.position,
+            self.camera.get_view_direction(),
+            REACH,
+            RaycastFilter::default(),
+        );
 
-        self.time += 0.01;
-        let sun_angle = self.time % (2.0 * std::f32::consts::PI);
-        let sun_height = sun_angle.sin();
-        let sun_distance = sun_angle.cos();
+        self.clock.apply_lighting(&mut self.light);
+        self.renderer.update_light_buffer(&self.queue, &self.light);
+        self.renderer.update_time(self.time);
 
-        self.light.direction = Vec3::new(sun_distance, -sun_height, 0.0).normalize();
-        self.light.update();
+        self.sky.sun_direction = self.clock.sun_direction();
+        self.sky.update(&self.camera);
         self.renderer
-            .update_light_buffer(&self.queue, &self.light.uniform);
+            .update_sky_buffer(&self.queue, &self.sky.uniform);
+    }
+
+    /// Advances the simulation by one fixed step of `dt` seconds.
+    fn tick(&mut self, dt: f32) {
+        if self.flying {
+            self.camera_controller.update_camera(&mut self.camera, dt);
+            self.player.set_eye_position(self.camera.position);
+        } else {
+            let input = self.camera_controller.player_input(&self.camera);
+            let world = &self.world;
+            self.player
+                .tick(&input, dt, |position| world.get_block(position));
+            self.camera.position = self.player.eye_position();
+        }
+
+        self.world.tick_blocks();
+        self.world.tick_entities(dt, self.player.position);
+        let bounds = self.player.bounds();
+        let reach = Vec3::splat(PICKUP_RANGE);
+        self.world.remove_entities(
+            &Aabb::new(bounds.min - reach, bounds.max + reach),
+            |entity| matches!(entity.kind, EntityKind::Item(_)) && entity.age > PICKUP_DELAY,
+        );
+
+        self.time += dt;
+        self.clock.tick(dt);
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -168,58 +473,106 @@ impl State {
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        let frustum = Frustum::from_matrix(self.camera.build_view_projection_matrix());
 
-        let mut vertices = Vec::new();
-        let chunks_lock = self.chunks.lock();
-        for chunk in chunks_lock.values() {
-            let (min, max) = chunk.get_bounds();
-            if frustum.is_box_visible(min, max) {
-                vertices.extend(chunk.generate_mesh());
+        for event in self.chunk_events.try_iter() {
+            if let ChunkEvent::Unloaded(pos) = event {
+                self.renderer.remove_chunk_mesh(pos);
             }
         }
-        drop(chunks_lock);
+        for (pos, mesh) in self.world.chunks_mut().take_meshes() {
+            self.renderer.upload_chunk_mesh(&self.device, pos, &mesh);
+        }
+        let distance = Vec3::splat(self.view_distance().world_distance());
+        let entities = self.world.entities_in(&Aabb::new(
+            self.camera.position - distance,
+            self.camera.position + distance,
+        ));
+        self.renderer.upload_entity_mesh(
+            &self.device,
+            &mesh_entities(&entities, self.timestep.alpha()),
+        );
 
-        self.renderer.update_vertices(&self.device, &vertices);
         self.renderer
             .render(&view, &self.device, &self.queue, &self.camera)?;
+        self.renderer.set_outline(
+            &self.queue,
+            self.target.map(|hit| Aabb::from_block(hit.position)),
+        );
+        self.renderer
+            .render_overlay(&view, &self.device, &self.queue);
         output.present();
 
+        if let Some(capture) = self.pending_capture.take() {
+            match self.capture(capture) {
+                Ok(path) => println!("Saved {:?}", path),
+                Err(e) => eprintln!("Capture failed: {}", e),
+            }
+        }
+
         Ok(())
     }
 
-    pub fn update_chunks(&mut self) {
-        let camera_chunk_pos = ChunkPos::from_world_pos(self.camera.position);
-        let mut chunks_to_keep = HashSet::new();
-
-        let horizontal_distance = 6;
-        let vertical_distance = 2;
-
-        for y in -vertical_distance..=vertical_distance {
-            for x in -horizontal_distance..=horizontal_distance {
-                for z in -horizontal_distance..=horizontal_distance {
-                    let chunk_pos = ChunkPos::new(
-                        camera_chunk_pos.x + x,
-                        camera_chunk_pos.y + y,
-                        camera_chunk_pos.z + z,
-                    );
-
-                    let distance_sq = x * x + y * y * 4 + z * z;
-                    if distance_sq <= horizontal_distance * horizontal_distance {
-                        chunks_to_keep.insert(chunk_pos);
-                        if !self.chunks.lock().contains_key(&chunk_pos) {
-                            self.chunk_worker.queue_chunk_generation(chunk_pos);
-                        }
+    /// Re-renders the current frame offscreen and writes it to a PNG in
+    /// `SCREENSHOT_DIR`. Uses whatever meshes the last `render` uploaded.
+    pub fn capture(&mut self, capture: Capture) -> Result<PathBuf, CaptureError> {
+        let (width, height) = (self.config.width, self.config.height);
+        let target = OffscreenTarget::new(&self.device, self.config.format, width, height);
+
+        let (pixels, width, height) = match capture {
+            Capture::Color => {
+                self.renderer
+                    .render(&target.view, &self.device, &self.queue, &self.camera)?;
+                (target.read_rgba(&self.device, &self.queue)?, width, height)
+            }
+            Capture::Depth => self.capture_depth(DepthSource::Scene)?,
+            Capture::ShadowMap { cascade } => {
+                self.capture_depth(DepthSource::ShadowCascade(cascade))?
+            }
+            Capture::Tiled { tiles } => {
+                let mut image = image::RgbaImage::new(width * tiles, height * tiles);
+                for y in 0..tiles {
+                    for x in 0..tiles {
+                        self.camera.tile_transform = tile_transform(tiles, x, y);
+                        self.sky.update(&self.camera);
+                        self.renderer
+                            .update_sky_buffer(&self.queue, &self.sky.uniform);
+                        self.renderer.render(
+                            &target.view,
+                            &self.device,
+                            &self.queue,
+                            &self.camera,
+                        )?;
+
+                        let tile = target.read_rgba(&self.device, &self.queue)?;
+                        let tile = image::RgbaImage::from_raw(width, height, tile)
+                            .expect("readback size matches the target");
+                        image::imageops::replace(
+                            &mut image,
+                            &tile,
+                            (x * width) as i64,
+                            (y * height) as i64,
+                        );
                     }
                 }
+
+                self.camera.tile_transform = Mat4::IDENTITY;
+                self.sky.update(&self.camera);
+                self.renderer
+                    .update_sky_buffer(&self.queue, &self.sky.uniform);
+                (image.into_raw(), width * tiles, height * tiles)
             }
-        }
+        };
 
-        self.chunks.lock().retain(|pos, _| {
-            let dx = pos.x - camera_chunk_pos.x;
-            let dy = pos.y - camera_chunk_pos.y;
-            let dz = pos.z - camera_chunk_pos.z;
-            dx * dx + dy * dy * 4 + dz * dz <= (horizontal_distance + 2) * (horizontal_distance + 2)
-        });
+        let path = capture_path(SCREENSHOT_DIR, capture)?;
+        image::save_buffer(&path, &pixels, width, height, image::ColorType::Rgba8)?;
+        Ok(path)
+    }
+
+    fn capture_depth(&self, source: DepthSource) -> Result<(Vec<u8>, u32, u32), CaptureError> {
+        let (width, height) = self.renderer.depth_view_size(source);
+        let target = OffscreenTarget::new(&self.device, DEPTH_VIEW_FORMAT, width, height);
+        self.renderer
+            .render_depth_view(&self.device, &self.queue, &target.view, source);
+        Ok((target.read_rgba(&self.device, &self.queue)?, width, height))
     }
 }
@@ -0,0 +1,190 @@
+use image::{imageops::FilterType, RgbaImage};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const BLOCK_TEXTURE_DIR: &str = "assets/textures";
+pub const BLOCK_TEXTURE_SIZE: u32 = 16;
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    pub fn create_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Uploads every block texture as its own layer of a `texture_2d_array`.
+    /// Layers never share texels, so mipmapping cannot bleed between blocks
+    /// the way it would in a packed atlas.
+    pub fn create_block_texture_array(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &BlockTextures,
+    ) -> Self {
+        let layer_count = textures.images.len().max(1) as u32;
+        let mip_level_count = BLOCK_TEXTURE_SIZE.ilog2() + 1;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Block Texture Array"),
+            size: wgpu::Extent3d {
+                width: BLOCK_TEXTURE_SIZE,
+                height: BLOCK_TEXTURE_SIZE,
+                depth_or_array_layers: layer_count,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        for (layer, image) in textures.images.iter().enumerate() {
+            for mip_level in 0..mip_level_count {
+                let size = BLOCK_TEXTURE_SIZE >> mip_level;
+                let mip = if mip_level == 0 {
+                    image.clone()
+                } else {
+                    image::imageops::resize(image, size, size, FilterType::Triangle)
+                };
+
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &mip,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * size),
+                        rows_per_image: Some(size),
+                    },
+                    wgpu::Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+/// Block face images loaded from `BLOCK_TEXTURE_DIR`, keyed by file stem
+/// (e.g. `grass_top.png` is looked up as `"grass_top"`).
+#[derive(Default)]
+pub struct BlockTextures {
+    layers: HashMap<String, i32>,
+    images: Vec<RgbaImage>,
+}
+
+impl BlockTextures {
+    pub fn load(dir: impl AsRef<Path>) -> Self {
+        let mut textures = Self::default();
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return textures;
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            match image::open(&path) {
+                Ok(image) => {
+                    let mut image = image.to_rgba8();
+                    if image.dimensions() != (BLOCK_TEXTURE_SIZE, BLOCK_TEXTURE_SIZE) {
+                        image = image::imageops::resize(
+                            &image,
+                            BLOCK_TEXTURE_SIZE,
+                            BLOCK_TEXTURE_SIZE,
+                            FilterType::Nearest,
+                        );
+                    }
+                    textures
+                        .layers
+                        .insert(name.to_string(), textures.images.len() as i32);
+                    textures.images.push(image);
+                }
+                Err(e) => eprintln!("Failed to load texture {:?}: {}", path, e),
+            }
+        }
+
+        textures
+    }
+
+    pub fn layer(&self, name: Option<&str>) -> Option<i32> {
+        name.and_then(|name| self.layers.get(name).copied())
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TICK_RATE: f32 = 60.0;
+/// Tick rates are clamped to this many per second and at least one.
+const MAX_TICK_RATE: f32 = 1000.0;
+// Caps how much simulation one slow frame can queue up, so a hitch (or a
+// breakpoint) doesn't turn into hundreds of catch-up ticks.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Turns variable frame times into a whole number of fixed-length simulation
+/// ticks, carrying the remainder over to the next frame.
+pub struct FixedTimestep {
+    tick: Duration,
+    accumulator: Duration,
+    last_update: Option<Instant>,
+}
+
+impl FixedTimestep {
+    /// A timestep running `tick_rate` ticks per second. Rates that aren't
+    /// finite fall back to the default, since a zero-length tick would never
+    /// stop ticking.
+    pub fn new(tick_rate: f32) -> Self {
+        let tick_rate = if tick_rate.is_finite() {
+            tick_rate.clamp(1.0, MAX_TICK_RATE)
+        } else {
+            DEFAULT_TICK_RATE
+        };
+        Self {
+            tick: Duration::from_secs_f32(1.0 / tick_rate),
+            accumulator: Duration::ZERO,
+            last_update: None,
+        }
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        1.0 / self.tick.as_secs_f32()
+    }
+
+    /// Length of one tick in seconds.
+    pub fn dt(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    /// Measures the time since the previous call and returns how many ticks
+    /// to run. The first call never runs any.
+    pub fn update(&mut self, now: Instant) -> u32 {
+        let frame_time = self
+            .last_update
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_update = Some(now);
+        self.advance(frame_time)
+    }
+
+    pub fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// How far the current frame is between the last tick and the next one,
+    /// for interpolating what gets rendered.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_rate_is_clamped() {
+        let rate = |tick_rate: f32| FixedTimestep::new(tick_rate).tick_rate();
+        assert!((rate(f32::INFINITY) - DEFAULT_TICK_RATE).abs() < 0.01);
+        assert!((rate(f32::NAN) - DEFAULT_TICK_RATE).abs() < 0.01);
+        assert!((rate(1e10) - MAX_TICK_RATE).abs() < 1.0);
+        assert!((rate(0.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn huge_tick_rate_still_finishes() {
+        let mut timestep = FixedTimestep::new(1e10);
+        let ticks = timestep.advance(Duration::from_secs(1));
+        assert!(ticks <= MAX_TICK_RATE as u32 / 4 + 1);
+    }
+
+    #[test]
+    fn remainder_carries_over() {
+        let mut timestep = FixedTimestep::new(10.0);
+        assert_eq!(timestep.advance(Duration::from_millis(150)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(60)), 1);
+    }
+}
@@ -2,7 +2,11 @@ mod engine;
 mod utils;
 mod world;
 
+use engine::headless::{image_difference, HeadlessRenderer, TerrainScene};
+use engine::settings::Settings;
 use engine::state::State;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::{Duration, Instant};
 use winit::{
     event::*,
@@ -10,47 +14,165 @@ use winit::{
     window::WindowBuilder,
 };
 
-fn main() {
+const HEADLESS_WIDTH: u32 = 640;
+const HEADLESS_HEIGHT: u32 = 360;
+const GOLDEN_TOLERANCE: u8 = 8;
+const GOLDEN_MAX_DIFFERENCE: f32 = 0.01;
+
+#[derive(Default)]
+struct HeadlessArgs {
+    output: PathBuf,
+    golden: Option<PathBuf>,
+    update_golden: bool,
+    fallback: bool,
+}
+
+impl HeadlessArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Option<Self> {
+        let mut parsed = Self::default();
+        let mut headless = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {
+                    headless = true;
+                    parsed.output = args.next()?.into();
+                }
+                "--golden" => parsed.golden = Some(args.next()?.into()),
+                "--update-golden" => parsed.update_golden = true,
+                "--fallback" => parsed.fallback = true,
+                _ => {}
+            }
+        }
+        headless.then_some(parsed)
+    }
+}
+
+/// Renders the fixed terrain scene offscreen, writes it to a PNG and
+/// optionally compares it against (or refreshes) a golden image.
+fn run_headless(args: HeadlessArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let scene = TerrainScene::new(HEADLESS_WIDTH, HEADLESS_HEIGHT, 4);
+    let mut renderer = pollster::block_on(HeadlessRenderer::new(
+        HEADLESS_WIDTH,
+        HEADLESS_HEIGHT,
+        args.fallback,
+        &scene.camera,
+    ))?;
+    let meshes = scene.meshes(renderer.block_textures());
+    let pixels = renderer.render(&scene.camera, &scene.light, &scene.sky, &meshes)?;
+
+    let (width, height) = (renderer.width(), renderer.height());
+    image::save_buffer(
+        &args.output,
+        &pixels,
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )?;
+
+    let Some(golden) = args.golden else {
+        return Ok(());
+    };
+    if args.update_golden {
+        image::save_buffer(&golden, &pixels, width, height, image::ColorType::Rgba8)?;
+        return Ok(());
+    }
+
+    let expected = image::open(&golden)?.to_rgba8();
+    if expected.dimensions() != (width, height) {
+        return Err(format!(
+            "{:?} is {:?}, expected {}x{}",
+            golden,
+            expected.dimensions(),
+            width,
+            height
+        )
+        .into());
+    }
+
+    let difference = image_difference(&pixels, expected.as_raw(), GOLDEN_TOLERANCE);
+    println!(
+        "{:.3}% of pixels differ from {:?}",
+        difference * 100.0,
+        golden
+    );
+    if difference > GOLDEN_MAX_DIFFERENCE {
+        return Err("rendered frame does not match the golden image".into());
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    if let Some(args) = HeadlessArgs::parse(std::env::args().skip(1)) {
+        return match run_headless(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("Rust Voxel Engine")
         .build(&event_loop)
         .unwrap();
 
-    let mut state = pollster::block_on(async { State::new(&window).await });
+    let settings = Settings::from_args(std::env::args().skip(1));
+    let mut state = pollster::block_on(async { State::new(&window, &settings).await });
 
     let mut frame_count = 0;
-    let mut fps = 0.0;
     let mut last_fps_update = Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             ref event,
             window_id,
-        } if window_id == window.id() => {
-            if !state.input(event) {
-                match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(physical_size) => {
-                        state.resize(*physical_size);
-                    }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        state.resize(**new_inner_size);
-                    }
-                    _ => {}
-                }
+        } if window_id == window.id() && !state.input(event) => match event {
+            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            WindowEvent::Resized(physical_size) => {
+                state.resize(*physical_size);
             }
-        }
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                state.resize(**new_inner_size);
+            }
+            _ => {}
+        },
         Event::RedrawRequested(window_id) if window_id == window.id() => {
             frame_count += 1;
             let now = Instant::now();
 
             if now.duration_since(last_fps_update) >= Duration::from_secs(1) {
-                fps = frame_count as f64 / now.duration_since(last_fps_update).as_secs_f64();
+                let fps = frame_count as f64 / now.duration_since(last_fps_update).as_secs_f64();
                 frame_count = 0;
                 last_fps_update = now;
 
-                window.set_title(&format!("Rust Voxel Engine | FPS: {:.1}", fps));
+                let clock = state.clock();
+                let (hour, minute) = clock.hour_minute();
+                let target = match state.target() {
+                    Some(hit) => format!(
+                        " | Target: {:?} at {} {} {}",
+                        hit.block, hit.position.x, hit.position.y, hit.position.z
+                    ),
+                    None => String::new(),
+                };
+                window.set_title(&format!(
+                    "Rust Voxel Engine | FPS: {:.1} | TPS: {:.0} | View: {}x{} | Cache: {:.0}% | {} | Block: {:?} | Day {} {:02}:{:02}{}{}{}",
+                    fps,
+                    state.tick_rate(),
+                    state.view_distance().horizontal,
+                    state.view_distance().vertical,
+                    state.cache_stats().hit_rate() * 100.0,
+                    if state.is_flying() { "Flying" } else { "Walking" },
+                    state.selected_block(),
+                    clock.day() + 1,
+                    hour,
+                    minute,
+                    if clock.is_night() { " (night)" } else { "" },
+                    if clock.is_paused() { " [paused]" } else { "" },
+                    target,
+                ));
             }
 
             state.update();
@@ -64,6 +186,7 @@ fn main() {
         Event::MainEventsCleared => {
             window.request_redraw();
         }
+        Event::LoopDestroyed => state.save_world(),
         _ => {}
     });
 }
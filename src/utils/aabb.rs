@@ -0,0 +1,70 @@
+use glam::{IVec3, Vec3};
+
+// How far apart two faces can be and still count as touching, so rounding in
+// a resting body's position doesn't let it slip into what it's resting on.
+const CONTACT_EPSILON: f32 = 1e-4;
+
+/// Axis-aligned bounding box in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// The unit cube occupied by the block at `position`.
+    pub fn from_block(position: IVec3) -> Self {
+        let min = position.as_vec3();
+        Self::new(min, min + Vec3::ONE)
+    }
+
+    /// True if the boxes overlap; touching faces don't count.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+
+    pub fn translate(&self, offset: Vec3) -> Aabb {
+        Self::new(self.min + offset, self.max + offset)
+    }
+
+    /// The box covering everything this one passes through while moving by
+    /// `motion`.
+    pub fn expand_towards(&self, motion: Vec3) -> Aabb {
+        Self::new(
+            self.min + motion.min(Vec3::ZERO),
+            self.max + motion.max(Vec3::ZERO),
+        )
+    }
+
+    /// Every block position the box overlaps.
+    pub fn block_positions(&self) -> impl Iterator<Item = IVec3> {
+        let min = self.min.floor().as_ivec3();
+        let max = self.max.ceil().as_ivec3();
+        (min.x..max.x).flat_map(move |x| {
+            (min.y..max.y).flat_map(move |y| (min.z..max.z).map(move |z| IVec3::new(x, y, z)))
+        })
+    }
+
+    /// Shortens `offset`, a move of `moving` along `axis`, so it stops at this
+    /// box instead of passing into it.
+    pub fn clip_axis(&self, moving: &Aabb, axis: usize, offset: f32) -> f32 {
+        let overlaps_across = (0..3).filter(|&other| other != axis).all(|other| {
+            self.min[other] < moving.max[other] && moving.min[other] < self.max[other]
+        });
+        if !overlaps_across {
+            return offset;
+        }
+
+        if offset > 0.0 && moving.max[axis] <= self.min[axis] + CONTACT_EPSILON {
+            offset.min(self.min[axis] - moving.max[axis])
+        } else if offset < 0.0 && moving.min[axis] >= self.max[axis] - CONTACT_EPSILON {
+            offset.max(self.max[axis] - moving.min[axis])
+        } else {
+            offset
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use glam::IVec3;
+
+/// Hermite interpolation between 0.0 and 1.0 as `x` goes from `edge0` to `edge1`,
+/// same as WGSL's `smoothstep`.
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Mixes a block or chunk position into a seed, so things randomized per
+/// position come out the same every run.
+pub fn position_seed(position: IVec3) -> u64 {
+    (position.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (position.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (position.z as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+}
@@ -1 +1,3 @@
+pub mod aabb;
 pub mod frustum;
+pub mod math;
@@ -0,0 +1,351 @@
+use crate::utils::aabb::Aabb;
+use crate::world::block::{BlockType, FACE_NEIGHBORS};
+use crate::world::block_updates::{self, BlockUpdate, BlockUpdates, MAX_UPDATES_PER_TICK};
+use crate::world::chunk::{Chunk, ChunkPos};
+use crate::world::chunk_events::BlockChange;
+use crate::world::chunk_manager::ChunkManager;
+use crate::world::entity::{Entity, EntityKind};
+use crate::world::mob::{self, MobContext};
+use crate::world::pathfinding::{Pathfinder, DEFAULT_PATH_BUDGET};
+use crate::world::raycast::{raycast, RaycastFilter, RaycastHit};
+use glam::{IVec3, Vec3};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+/// Block access in world coordinates on top of the loaded chunks. Anything
+/// that isn't loaded reads and writes as `None`.
+pub struct World {
+    chunks: ChunkManager,
+    pathfinder: Pathfinder,
+    rng: StdRng,
+    updates: BlockUpdates,
+}
+
+impl World {
+    pub fn new(chunks: ChunkManager) -> Self {
+        Self {
+            chunks,
+            pathfinder: Pathfinder::new(DEFAULT_PATH_BUDGET),
+            rng: StdRng::from_entropy(),
+            updates: BlockUpdates::default(),
+        }
+    }
+
+    pub fn chunks(&self) -> &ChunkManager {
+        &self.chunks
+    }
+
+    pub fn chunks_mut(&mut self) -> &mut ChunkManager {
+        &mut self.chunks
+    }
+
+    /// The block at `position`, or `None` if its chunk isn't loaded.
+    pub fn get_block(&self, position: IVec3) -> Option<BlockType> {
+        block_in(&self.chunks.loaded_chunks(), position)
+    }
+
+    /// Replaces the block at `position` and returns the one that was there,
+    /// or `None` (changing nothing) if its chunk isn't loaded. The chunk, and
+    /// any neighbor sharing the changed face, is remeshed.
+    pub fn set_block(&mut self, position: IVec3, block: BlockType) -> Option<BlockType> {
+        let old = self.get_block(position)?;
+        let changes = self.chunks.set_blocks([(position, block)]);
+        self.changed(&changes);
+        Some(old)
+    }
+
+    /// Runs the block updates due this tick, such as water flowing, sand
+    /// falling and fire spreading, and schedules updates around whatever they
+    /// change. Updates aren't saved; a chunk unloaded mid-flow stays as it was.
+    pub fn tick_blocks(&mut self) {
+        let due = self.updates.advance(MAX_UPDATES_PER_TICK);
+        if due.is_empty() {
+            return;
+        }
+        let tick = self.updates.tick();
+
+        let mut updated = Vec::new();
+        let mut falling = Vec::new();
+        {
+            let chunks = self.chunks.loaded_chunks();
+            for position in due {
+                let Some(block) = block_in(&chunks, position) else {
+                    continue;
+                };
+                let updates = block_updates::update(position, block, tick, |position| {
+                    block_in(&chunks, position)
+                });
+                for update in updates {
+                    match update {
+                        BlockUpdate::Set(position, new) => updated.push((position, new)),
+                        BlockUpdate::Fall(position) => {
+                            updated.push((position, BlockType::Air));
+                            falling.push(Entity::new(
+                                EntityKind::FallingBlock(block),
+                                position.as_vec3() + Vec3::new(0.5, 0.0, 0.5),
+                                Vec3::ZERO,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        let changes = self.chunks.set_blocks(updated);
+        self.changed(&changes);
+        for entity in falling {
+            self.spawn_entity(entity);
+        }
+    }
+
+    /// Lets everything that depends on blocks know about `changes`, and
+    /// schedules updates for the blocks around them that react.
+    fn changed(&mut self, changes: &[BlockChange]) {
+        let chunks = self.chunks.loaded_chunks();
+        for change in changes {
+            self.pathfinder.invalidate(change.position);
+            for offset in std::iter::once(IVec3::ZERO).chain(FACE_NEIGHBORS) {
+                let position = change.position + offset;
+                let Some(block) = block_in(&chunks, position) else {
+                    continue;
+                };
+                // Air only reacts by filling with water, so without any next
+                // to it the update would do nothing.
+                let wet = || {
+                    FACE_NEIGHBORS.iter().any(|offset| {
+                        block_in(&chunks, position + *offset).is_some_and(|block| block.is_liquid())
+                    })
+                };
+                if block == BlockType::Air && !wet() {
+                    continue;
+                }
+                if let Some(delay) = block_updates::update_delay(block) {
+                    self.updates.schedule(position, delay);
+                }
+            }
+        }
+    }
+
+    /// The first block along the ray that `filter` doesn't skip, if one is
+    /// within `max_distance` and nothing unloaded is in the way.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        filter: RaycastFilter,
+    ) -> Option<RaycastHit> {
+        raycast(origin, direction, max_distance, filter, |position| {
+            self.get_block(position)
+        })
+    }
+
+    /// Adds `entity` to the chunk it's in. Returns false, dropping it, if that
+    /// chunk isn't loaded.
+    pub fn spawn_entity(&mut self, entity: Entity) -> bool {
+        let mut chunks = self.chunks.loaded_chunks();
+        match chunks.get_mut(&entity.chunk()) {
+            Some(chunk) => {
+                chunk.add_entity(entity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies of the entities whose boxes overlap `bounds`. Only the chunks
+    /// the box covers are searched.
+    pub fn entities_in(&self, bounds: &Aabb) -> Vec<Entity> {
+        let chunks = self.chunks.loaded_chunks();
+        chunks_in(bounds)
+            .filter_map(|pos| chunks.get(&pos))
+            .flat_map(|chunk| chunk.entities())
+            .filter(|entity| entity.bounds().intersects(bounds))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes and returns the entities overlapping `bounds` that `predicate`
+    /// accepts.
+    pub fn remove_entities(
+        &mut self,
+        bounds: &Aabb,
+        predicate: impl Fn(&Entity) -> bool,
+    ) -> Vec<Entity> {
+        let mut chunks = self.chunks.loaded_chunks();
+        let mut removed = Vec::new();
+        for pos in chunks_in(bounds) {
+            let Some(chunk) = chunks.get_mut(&pos) else {
+                continue;
+            };
+            if !chunk
+                .entities()
+                .iter()
+                .any(|entity| entity.bounds().intersects(bounds) && predicate(entity))
+            {
+                continue;
+            }
+            let entities = chunk.take_entities();
+            let taken = entities.len();
+            let (gone, kept): (Vec<_>, Vec<_>) = entities
+                .into_iter()
+                .partition(|entity| entity.bounds().intersects(bounds) && predicate(entity));
+            removed.extend(gone);
+            chunk.return_entities(kept, taken);
+        }
+        removed
+    }
+
+    /// Advances every entity in a loaded chunk by `dt` seconds and moves each
+    /// into the chunk it ends up in. Unloaded blocks are solid to entities,
+    /// so they can't wander out of the loaded world. Mobs react to the player
+    /// standing at `player`.
+    pub fn tick_entities(&mut self, dt: f32, player: Vec3) {
+        self.pathfinder.begin_tick();
+        let mut context = MobContext {
+            pathfinder: &mut self.pathfinder,
+            rng: &mut self.rng,
+            player,
+        };
+        let mut chunks = self.chunks.loaded_chunks();
+        let mut entities = Vec::new();
+        let mut taken = Vec::new();
+        let mut staying: HashMap<ChunkPos, Vec<Entity>> = HashMap::new();
+        let mut landed = Vec::new();
+        for (pos, chunk) in chunks.iter_mut() {
+            let moving = chunk.take_entities();
+            if !moving.is_empty() {
+                taken.push((*pos, moving.len()));
+                entities.extend(moving.into_iter().map(|entity| (*pos, entity)));
+            }
+        }
+
+        for (pos, mut entity) in entities {
+            if let EntityKind::Mob(behavior) = entity.kind {
+                mob::think(
+                    &mut entity.navigation,
+                    behavior,
+                    entity.position,
+                    &mut context,
+                    dt,
+                    |position| block_in(&chunks, position),
+                );
+            }
+            if !entity.tick(dt, |position| block_in(&chunks, position)) {
+                continue;
+            }
+            // Waits on top of unloaded chunks rather than landing on them.
+            if let Some((position, block)) = entity.landed() {
+                if block_in(&chunks, position - IVec3::Y).is_some() {
+                    landed.push((entity.position, position, block));
+                    continue;
+                }
+            }
+            // Only crossing into another chunk changes what either one saves.
+            let to = entity.chunk();
+            match chunks.get_mut(&to) {
+                Some(chunk) if to != pos => chunk.add_entity(entity),
+                _ => staying.entry(pos).or_default().push(entity),
+            }
+        }
+        for (pos, count) in taken {
+            if let Some(chunk) = chunks.get_mut(&pos) {
+                chunk.return_entities(staying.remove(&pos).unwrap_or_default(), count);
+            }
+        }
+        drop(chunks);
+
+        // A falling block that lands where it can't be placed drops as an item.
+        // It only displaces what would have let it through anyway, never a
+        // water source, which would otherwise be gone for good.
+        for (drop_at, position, block) in landed {
+            if self.get_block(position).is_some_and(|old| {
+                matches!(
+                    old,
+                    BlockType::Air | BlockType::Fire(_) | BlockType::FlowingWater(_)
+                )
+            }) {
+                self.set_block(position, block);
+            } else {
+                self.spawn_entity(Entity::new(EntityKind::Item(block), drop_at, Vec3::ZERO));
+            }
+        }
+    }
+}
+
+/// The block at `position` among `chunks`, or `None` if its chunk isn't
+/// there.
+pub(crate) fn block_in(chunks: &HashMap<ChunkPos, Chunk>, position: IVec3) -> Option<BlockType> {
+    let chunk = chunks.get(&ChunkPos::from_block_pos(position))?;
+    let [x, y, z] = ChunkPos::local_block_pos(position);
+    Some(chunk.get_block(x, y, z))
+}
+
+/// Every chunk position `bounds` overlaps.
+fn chunks_in(bounds: &Aabb) -> impl Iterator<Item = ChunkPos> {
+    let min = ChunkPos::from_world_pos(bounds.min);
+    let max = ChunkPos::from_world_pos(bounds.max);
+    (min.x..=max.x).flat_map(move |x| {
+        (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| ChunkPos::new(x, y, z)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::texture::BlockTextures;
+    use crate::world::chunk_cache::DEFAULT_CACHE_BUDGET;
+    use crate::world::chunk_manager::ViewDistance;
+    use crate::world::test_layout::Layout;
+    use std::sync::Arc;
+
+    fn world(layout: Layout) -> World {
+        let chunks = ChunkManager::new(
+            ViewDistance::default(),
+            1,
+            DEFAULT_CACHE_BUDGET,
+            Arc::new(BlockTextures::default()),
+        );
+        chunks.loaded_chunks().extend(layout.into_chunks());
+        World::new(chunks)
+    }
+
+    /// Drops a block of sand from `at` and gives it time to come to rest.
+    fn drop_sand(world: &mut World, at: Vec3) {
+        world.spawn_entity(Entity::new(
+            EntityKind::FallingBlock(BlockType::Sand),
+            at,
+            Vec3::ZERO,
+        ));
+        for _ in 0..300 {
+            world.tick_entities(1.0 / 60.0, Vec3::splat(100.0));
+        }
+    }
+
+    #[test]
+    fn falling_sand_never_replaces_a_water_source() {
+        let mut layout = Layout::new(IVec3::splat(-16), IVec3::splat(15));
+        layout
+            .fill(
+                IVec3::new(-4, -1, -4),
+                IVec3::new(4, -1, 4),
+                BlockType::Stone,
+            )
+            .fill(IVec3::new(-4, 0, -4), IVec3::new(0, 1, 4), BlockType::Water)
+            .set(IVec3::new(3, 0, 0), BlockType::FlowingWater(3));
+        let mut world = world(layout);
+
+        drop_sand(&mut world, Vec3::new(-1.5, 6.0, 0.5));
+        assert_eq!(
+            world.get_block(IVec3::new(-2, 0, 0)),
+            Some(BlockType::Water)
+        );
+        let everywhere = Aabb::new(Vec3::splat(-16.0), Vec3::splat(16.0));
+        let items = world.entities_in(&everywhere);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, EntityKind::Item(BlockType::Sand));
+
+        drop_sand(&mut world, Vec3::new(3.5, 6.0, 0.5));
+        assert_eq!(world.get_block(IVec3::new(3, 0, 0)), Some(BlockType::Sand));
+    }
+}
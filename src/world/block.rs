@@ -1,3 +1,5 @@
+use glam::IVec3;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockType {
     Air,
@@ -7,11 +9,54 @@ pub enum BlockType {
     Wood,
     Leaves,
     Sand,
+    /// Source water, which never drains away.
     Water,
+    /// Water spreading from a source, from `MAX_FLOW_LEVEL` next to it down
+    /// to 1 at the edge of the spread.
+    FlowingWater(u8),
     Bedrock,
     DiamondOre,
     IronOre,
     CoalOre,
+    Gravel,
+    /// What's left of burned wood.
+    Ash,
+    /// Burns for `MAX_FIRE_AGE` updates, counting up from 0.
+    Fire(u8),
+}
+
+pub const MAX_FLOW_LEVEL: u8 = 7;
+pub const MAX_FIRE_AGE: u8 = 7;
+
+/// Offsets to the six blocks sharing a face with a block.
+pub const FACE_NEIGHBORS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Blocks the player can pick with the number keys, in key order.
+pub const PLACEABLE_BLOCKS: [BlockType; 10] = [
+    BlockType::Grass,
+    BlockType::Dirt,
+    BlockType::Stone,
+    BlockType::Wood,
+    BlockType::Leaves,
+    BlockType::Sand,
+    BlockType::Water,
+    BlockType::IronOre,
+    BlockType::DiamondOre,
+    BlockType::Gravel,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderLayer {
+    Opaque,
+    Cutout,
+    Translucent,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,8 +70,134 @@ pub enum BlockFace {
 }
 
 impl BlockType {
+    /// Stable id used when saving chunks. Never renumber existing blocks.
+    pub fn id(&self) -> u8 {
+        match self {
+            BlockType::Air => 0,
+            BlockType::Dirt => 1,
+            BlockType::Grass => 2,
+            BlockType::Stone => 3,
+            BlockType::Wood => 4,
+            BlockType::Leaves => 5,
+            BlockType::Sand => 6,
+            BlockType::Water => 7,
+            BlockType::Bedrock => 8,
+            BlockType::DiamondOre => 9,
+            BlockType::IronOre => 10,
+            BlockType::CoalOre => 11,
+            BlockType::FlowingWater(level) => 11 + (*level).clamp(1, MAX_FLOW_LEVEL),
+            BlockType::Gravel => 19,
+            BlockType::Ash => 20,
+            BlockType::Fire(age) => 21 + (*age).min(MAX_FIRE_AGE),
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => BlockType::Air,
+            1 => BlockType::Dirt,
+            2 => BlockType::Grass,
+            3 => BlockType::Stone,
+            4 => BlockType::Wood,
+            5 => BlockType::Leaves,
+            6 => BlockType::Sand,
+            7 => BlockType::Water,
+            8 => BlockType::Bedrock,
+            9 => BlockType::DiamondOre,
+            10 => BlockType::IronOre,
+            11 => BlockType::CoalOre,
+            12..=18 => BlockType::FlowingWater(id - 11),
+            19 => BlockType::Gravel,
+            20 => BlockType::Ash,
+            21..=28 => BlockType::Fire(id - 21),
+            _ => return None,
+        })
+    }
+
     pub fn is_transparent(&self) -> bool {
-        matches!(self, BlockType::Air | BlockType::Water | BlockType::Leaves)
+        matches!(
+            self,
+            BlockType::Air | BlockType::Leaves | BlockType::Fire(_)
+        ) || self.is_liquid()
+    }
+
+    pub fn is_liquid(&self) -> bool {
+        matches!(self, BlockType::Water | BlockType::FlowingWater(_))
+    }
+
+    /// Whether bodies collide with this block.
+    pub fn is_solid(&self) -> bool {
+        !matches!(self, BlockType::Air | BlockType::Fire(_)) && !self.is_liquid()
+    }
+
+    /// Whether the block falls when there's nothing solid under it.
+    pub fn falls(&self) -> bool {
+        matches!(self, BlockType::Sand | BlockType::Gravel | BlockType::Ash)
+    }
+
+    /// Chance, from 0 to 1, that fire next to the block spreads to the air
+    /// around it on each fire update. 0 for blocks that don't burn.
+    pub fn flammability(&self) -> f32 {
+        match self {
+            BlockType::Wood => 0.3,
+            BlockType::Leaves => 0.6,
+            _ => 0.0,
+        }
+    }
+
+    /// About how many ticks the block lasts next to fire before it burns
+    /// away. Only meaningful for flammable blocks.
+    pub fn burn_time(&self) -> u64 {
+        match self {
+            BlockType::Wood => 300,
+            BlockType::Leaves => 60,
+            _ => 0,
+        }
+    }
+
+    pub fn is_flammable(&self) -> bool {
+        self.flammability() > 0.0
+    }
+
+    /// What the block leaves behind once it has burned.
+    pub fn burned(&self) -> BlockType {
+        match self {
+            BlockType::Wood => BlockType::Ash,
+            _ => BlockType::Air,
+        }
+    }
+
+    /// Whether placing a block here simply overwrites it.
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self, BlockType::Air | BlockType::Fire(_)) || self.is_liquid()
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        !matches!(self, BlockType::Air | BlockType::Bedrock) && !self.is_liquid()
+    }
+
+    pub fn render_layer(&self) -> RenderLayer {
+        match self {
+            BlockType::Water | BlockType::FlowingWater(_) => RenderLayer::Translucent,
+            BlockType::Leaves | BlockType::Fire(_) => RenderLayer::Cutout,
+            _ => RenderLayer::Opaque,
+        }
+    }
+
+    pub fn opacity(&self) -> f32 {
+        match self {
+            BlockType::Water | BlockType::FlowingWater(_) => 0.6,
+            _ => 1.0,
+        }
+    }
+
+    pub fn is_face_visible_against(&self, neighbor: BlockType) -> bool {
+        if self.render_layer() == RenderLayer::Translucent && neighbor == *self
+            || self.is_liquid() && neighbor.is_liquid()
+        {
+            return false;
+        }
+        neighbor.is_transparent()
     }
 
     pub fn get_color(&self) -> [f32; 3] {
@@ -38,33 +209,57 @@ impl BlockType {
             BlockType::Wood => [0.5, 0.3, 0.2],
             BlockType::Leaves => [0.0, 0.5, 0.0],
             BlockType::Sand => [0.85, 0.8, 0.6],
-            BlockType::Water => [0.0, 0.3, 0.8],
+            BlockType::Water | BlockType::FlowingWater(_) => [0.0, 0.3, 0.8],
             BlockType::Bedrock => [0.2, 0.2, 0.2],
             BlockType::DiamondOre => [0.0, 0.8, 0.8],
             BlockType::IronOre => [0.8, 0.7, 0.6],
             BlockType::CoalOre => [0.2, 0.2, 0.2],
+            BlockType::Gravel => [0.55, 0.52, 0.5],
+            BlockType::Ash => [0.35, 0.35, 0.35],
+            BlockType::Fire(_) => [1.0, 0.5, 0.1],
         }
     }
 
     pub fn get_face_color(&self, face: BlockFace) -> [f32; 3] {
         let base_color = self.get_color();
-        match face {
-            BlockFace::Top => base_color,
-            BlockFace::Bottom => [
-                base_color[0] * 0.7,
-                base_color[1] * 0.7,
-                base_color[2] * 0.7,
-            ],
-            BlockFace::North | BlockFace::South => [
-                base_color[0] * 0.8,
-                base_color[1] * 0.8,
-                base_color[2] * 0.8,
-            ],
-            BlockFace::East | BlockFace::West => [
-                base_color[0] * 0.9,
-                base_color[1] * 0.9,
-                base_color[2] * 0.9,
-            ],
+        let shade = face.shade();
+        [
+            base_color[0] * shade,
+            base_color[1] * shade,
+            base_color[2] * shade,
+        ]
+    }
+
+    pub fn texture_name(&self, face: BlockFace) -> Option<&'static str> {
+        match (self, face) {
+            (BlockType::Air, _) => None,
+            (BlockType::Dirt, _) | (BlockType::Grass, BlockFace::Bottom) => Some("dirt"),
+            (BlockType::Grass, BlockFace::Top) => Some("grass_top"),
+            (BlockType::Grass, _) => Some("grass_side"),
+            (BlockType::Stone, _) => Some("stone"),
+            (BlockType::Wood, BlockFace::Top | BlockFace::Bottom) => Some("wood_top"),
+            (BlockType::Wood, _) => Some("wood_side"),
+            (BlockType::Leaves, _) => Some("leaves"),
+            (BlockType::Sand, _) => Some("sand"),
+            (BlockType::Water | BlockType::FlowingWater(_), _) => Some("water"),
+            (BlockType::Bedrock, _) => Some("bedrock"),
+            (BlockType::DiamondOre, _) => Some("diamond_ore"),
+            (BlockType::IronOre, _) => Some("iron_ore"),
+            (BlockType::CoalOre, _) => Some("coal_ore"),
+            (BlockType::Gravel, _) => Some("gravel"),
+            (BlockType::Ash, _) => Some("ash"),
+            (BlockType::Fire(_), _) => Some("fire"),
+        }
+    }
+}
+
+impl BlockFace {
+    pub fn shade(&self) -> f32 {
+        match self {
+            BlockFace::Top => 1.0,
+            BlockFace::Bottom => 0.7,
+            BlockFace::North | BlockFace::South => 0.8,
+            BlockFace::East | BlockFace::West => 0.9,
         }
     }
 }
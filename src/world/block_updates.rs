@@ -0,0 +1,132 @@
+use crate::utils::math::position_seed;
+use crate::world::block::BlockType;
+use crate::world::fire::{self, FIRE_DELAY};
+use crate::world::fluid::{self, FLOW_DELAY};
+use glam::IVec3;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, HashMap};
+
+/// Updates one tick may run; the rest wait for the next tick.
+pub const MAX_UPDATES_PER_TICK: usize = 4096;
+/// Seed for the randomness in block updates.
+const UPDATE_SEED: u64 = 1234;
+/// Ticks between a block losing its support and starting to fall.
+const FALL_DELAY: u64 = 2;
+
+/// A change an update makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockUpdate {
+    /// The block at the position turns into another.
+    Set(IVec3, BlockType),
+    /// The block at the position leaves the grid and falls as an entity.
+    Fall(IVec3),
+}
+
+/// Ticks after a change next to `block` before it's updated, or `None` if
+/// it never reacts to its neighbors.
+pub fn update_delay(block: BlockType) -> Option<u64> {
+    match block {
+        _ if block.falls() => Some(FALL_DELAY),
+        BlockType::Fire(_) => Some(FIRE_DELAY),
+        // Water may flow into or out of these.
+        BlockType::Air | BlockType::FlowingWater(_) => Some(FLOW_DELAY),
+        _ => None,
+    }
+}
+
+/// Randomness for an update of `position` on `tick`. It depends on nothing
+/// else, so the same edits play out the same way whatever order updates run
+/// in.
+fn update_rng(position: IVec3, tick: u64) -> StdRng {
+    StdRng::seed_from_u64(
+        UPDATE_SEED ^ position_seed(position) ^ tick.wrapping_mul(0x27D4_EB2F_1656_67C5),
+    )
+}
+
+/// The changes updating `block` at `position` on `tick` makes, if any.
+pub fn update(
+    position: IVec3,
+    block: BlockType,
+    tick: u64,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) -> Vec<BlockUpdate> {
+    match block {
+        BlockType::Fire(age) => {
+            fire::burn(position, age, &mut update_rng(position, tick), block_at)
+                .into_iter()
+                .map(|(position, block)| BlockUpdate::Set(position, block))
+                .collect()
+        }
+        _ if block.falls() => {
+            let below = block_at(position - IVec3::Y);
+            if below.is_some_and(|below| !below.is_solid()) {
+                vec![BlockUpdate::Fall(position)]
+            } else {
+                Vec::new()
+            }
+        }
+        _ => fluid::flow(position, block, block_at)
+            .map(|new| BlockUpdate::Set(position, new))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Block positions waiting to be updated, by the tick they're due. A position
+/// is only queued once, at the earliest tick asked for.
+#[derive(Default)]
+pub struct BlockUpdates {
+    tick: u64,
+    queue: BTreeMap<u64, Vec<IVec3>>,
+    scheduled: HashMap<IVec3, u64>,
+}
+
+impl BlockUpdates {
+    /// Ticks advanced so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Queues an update of `position` `delay` ticks from now (at least one).
+    pub fn schedule(&mut self, position: IVec3, delay: u64) {
+        let due = self.tick + delay.max(1);
+        if self.scheduled.get(&position).is_some_and(|&at| at <= due) {
+            return;
+        }
+        self.scheduled.insert(position, due);
+        self.queue.entry(due).or_default().push(position);
+    }
+
+    /// Moves on a tick and returns up to `limit` positions whose updates are
+    /// due, oldest first.
+    pub fn advance(&mut self, limit: usize) -> Vec<IVec3> {
+        self.tick += 1;
+        let mut due = Vec::new();
+        while due.len() < limit {
+            let Some(mut entry) = self.queue.first_entry() else {
+                break;
+            };
+            if *entry.key() > self.tick {
+                break;
+            }
+
+            let at = *entry.key();
+            let positions = entry.get_mut();
+            while due.len() < limit {
+                let Some(position) = positions.pop() else {
+                    break;
+                };
+                // Skip entries superseded by an earlier schedule.
+                if self.scheduled.get(&position) == Some(&at) {
+                    self.scheduled.remove(&position);
+                    due.push(position);
+                }
+            }
+            if positions.is_empty() {
+                entry.remove();
+            }
+        }
+        due
+    }
+}
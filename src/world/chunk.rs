@@ -1,11 +1,137 @@
-use super::block::{BlockFace, BlockType};
+use super::block::{BlockFace, BlockType, RenderLayer, MAX_FLOW_LEVEL};
+use super::entity::Entity;
 use crate::engine::renderer::Vertex;
-use glam::Vec3;
+use crate::engine::texture::BlockTextures;
+use crate::utils::aabb::Aabb;
+use crate::utils::math::position_seed;
+use glam::{IVec3, Vec3};
 use noise::{NoiseFn, Perlin};
 use rand::prelude::*;
 use rand::Rng;
 
 pub const CHUNK_SIZE: usize = 16;
+pub const WATER_SURFACE_HEIGHT: f32 = 0.875;
+
+struct FaceGeometry {
+    face: BlockFace,
+    offset: [i32; 3],
+    normal: [f32; 3],
+    corners: [[f32; 3]; 6],
+}
+
+const FACES: [FaceGeometry; 6] = [
+    FaceGeometry {
+        face: BlockFace::Top,
+        offset: [0, 1, 0],
+        normal: [0.0, 1.0, 0.0],
+        corners: [
+            [0.0, 1.0, 0.0],
+            [0.0, 1.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 1.0, 0.0],
+        ],
+    },
+    FaceGeometry {
+        face: BlockFace::Bottom,
+        offset: [0, -1, 0],
+        normal: [0.0, -1.0, 0.0],
+        corners: [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0],
+        ],
+    },
+    FaceGeometry {
+        face: BlockFace::North,
+        offset: [0, 0, -1],
+        normal: [0.0, 0.0, -1.0],
+        corners: [
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ],
+    },
+    FaceGeometry {
+        face: BlockFace::South,
+        offset: [0, 0, 1],
+        normal: [0.0, 0.0, 1.0],
+        corners: [
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+        ],
+    },
+    FaceGeometry {
+        face: BlockFace::East,
+        offset: [1, 0, 0],
+        normal: [1.0, 0.0, 0.0],
+        corners: [
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 0.0, 1.0],
+        ],
+    },
+    FaceGeometry {
+        face: BlockFace::West,
+        offset: [-1, 0, 0],
+        normal: [-1.0, 0.0, 0.0],
+        corners: [
+            [0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0],
+        ],
+    },
+];
+
+fn face_uv(face: BlockFace, corner: [f32; 3]) -> [f32; 2] {
+    match face {
+        BlockFace::Top | BlockFace::Bottom => [corner[0], corner[2]],
+        BlockFace::North | BlockFace::South => [corner[0], 1.0 - corner[1]],
+        BlockFace::East | BlockFace::West => [corner[2], 1.0 - corner[1]],
+    }
+}
+
+/// Appends an untextured box covering `bounds`, shaded per face like
+/// untextured blocks.
+pub fn push_box(vertices: &mut Vec<Vertex>, bounds: &Aabb, color: [f32; 3]) {
+    let size = bounds.max - bounds.min;
+    for face in &FACES {
+        let shade = face.face.shade();
+        for corner in face.corners {
+            vertices.push(Vertex {
+                position: (bounds.min + Vec3::from(corner) * size).to_array(),
+                color: [color[0] * shade, color[1] * shade, color[2] * shade, 1.0],
+                normal: face.normal,
+                uv: face_uv(face.face, corner),
+                texture: -1,
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ChunkMesh {
+    pub opaque: Vec<Vertex>,
+    pub transparent: Vec<Vertex>,
+    pub water: Vec<Vertex>,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChunkPos {
@@ -27,19 +153,56 @@ impl ChunkPos {
         }
     }
 
-    pub fn to_world_pos(&self) -> Vec3 {
+    /// The chunk containing the world block at `position`. Rounds towards
+    /// negative infinity, so block -1 is in chunk -1.
+    pub fn from_block_pos(position: IVec3) -> Self {
+        let chunk = position.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        Self::new(chunk.x, chunk.y, chunk.z)
+    }
+
+    /// Where the world block at `position` sits inside its chunk.
+    pub fn local_block_pos(position: IVec3) -> [usize; 3] {
+        let local = position.rem_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        [local.x as usize, local.y as usize, local.z as usize]
+    }
+
+    pub fn to_world_pos(self) -> Vec3 {
         Vec3::new(
             self.x as f32 * CHUNK_SIZE as f32,
             self.y as f32 * CHUNK_SIZE as f32,
             self.z as f32 * CHUNK_SIZE as f32,
         )
     }
+
+    /// The six chunks sharing a face with this one, in the same order as `FACES`.
+    pub fn neighbors(self) -> [ChunkPos; 6] {
+        FACES.map(|face| {
+            ChunkPos::new(
+                self.x + face.offset[0],
+                self.y + face.offset[1],
+                self.z + face.offset[2],
+            )
+        })
+    }
+}
+
+/// Copies of the chunks around one being meshed, ordered like
+/// `ChunkPos::neighbors`. Faces against a missing neighbor are always drawn.
+pub struct ChunkNeighbors([Option<Chunk>; 6]);
+
+impl ChunkNeighbors {
+    pub fn new(chunks: [Option<Chunk>; 6]) -> Self {
+        Self(chunks)
+    }
 }
 
 #[derive(Clone)]
 pub struct Chunk {
     pub position: Vec3,
     blocks: [[[BlockType; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    entities: Vec<Entity>,
+    // Set by any change since the chunk was generated or last saved.
+    dirty: bool,
 }
 
 impl Chunk {
@@ -47,12 +210,56 @@ impl Chunk {
         Self {
             position,
             blocks: [[[BlockType::Air; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+            entities: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    /// Whether the chunk differs from what's on disk (or what the generator
+    /// would produce, if it was never saved).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Bytes the chunk takes up in memory, including its entity list.
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<Chunk>() + self.entities.capacity() * std::mem::size_of::<Entity>()
+    }
+
+    /// Adds an entity that spawned here or moved in from another chunk.
+    pub fn add_entity(&mut self, entity: Entity) {
+        self.entities.push(entity);
+        self.dirty = true;
+    }
+
+    /// Removes and returns every entity so they can be updated outside the
+    /// chunk. The ones still here go back with `return_entities`.
+    pub fn take_entities(&mut self) -> Vec<Entity> {
+        std::mem::take(&mut self.entities)
+    }
+
+    /// Puts back entities from `take_entities`, which took `taken`. Moving
+    /// around inside the chunk isn't a change worth saving, so the chunk is
+    /// only marked dirty if some didn't come back, having despawned or left.
+    pub fn return_entities(&mut self, entities: Vec<Entity>, taken: usize) {
+        if entities.len() < taken {
+            self.dirty = true;
         }
+        self.entities.extend(entities);
     }
 
     pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockType) {
         if x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE {
             self.blocks[x][y][z] = block;
+            self.dirty = true;
         }
     }
 
@@ -64,15 +271,8 @@ impl Chunk {
         }
     }
 
-    pub fn get_bounds(&self) -> (Vec3, Vec3) {
-        let min = self.position;
-        let max =
-            self.position + Vec3::new(CHUNK_SIZE as f32, CHUNK_SIZE as f32, CHUNK_SIZE as f32);
-        (min, max)
-    }
-
-    pub fn generate_mesh(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
+    pub fn generate_mesh(&self, neighbors: &ChunkNeighbors, textures: &BlockTextures) -> ChunkMesh {
+        let mut mesh = ChunkMesh::default();
 
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
@@ -82,386 +282,127 @@ impl Chunk {
                         continue;
                     }
 
-                    if y == CHUNK_SIZE - 1 || self.get_block(x, y + 1, z).is_transparent() {
-                        let normal = [0.0, 1.0, 0.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Top),
-                                normal,
-                            },
-                        ]);
-                    }
-
-                    if y == 0 || self.get_block(x, y - 1, z).is_transparent() {
-                        let normal = [0.0, -1.0, 0.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::Bottom),
-                                normal,
-                            },
-                        ]);
-                    }
+                    let height = self.block_height(neighbors, block, x, y, z);
 
-                    if z == 0 || self.get_block(x, y, z - 1).is_transparent() {
-                        let normal = [0.0, 0.0, -1.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::North),
-                                normal,
-                            },
-                        ]);
-                    }
+                    for face in &FACES {
+                        if !self.is_face_visible(neighbors, block, x, y, z, face.offset) {
+                            continue;
+                        }
 
-                    if z == CHUNK_SIZE - 1 || self.get_block(x, y, z + 1).is_transparent() {
-                        let normal = [0.0, 0.0, 1.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::South),
-                                normal,
-                            },
-                        ]);
+                        let vertices = match block.render_layer() {
+                            _ if block.is_liquid() && face.face == BlockFace::Top => {
+                                &mut mesh.water
+                            }
+                            RenderLayer::Opaque | RenderLayer::Cutout => &mut mesh.opaque,
+                            RenderLayer::Translucent => &mut mesh.transparent,
+                        };
+                        self.push_face(vertices, block, [x, y, z], height, face, textures);
                     }
+                }
+            }
+        }
 
-                    if x == CHUNK_SIZE - 1 || self.get_block(x + 1, y, z).is_transparent() {
-                        let normal = [1.0, 0.0, 0.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1.0 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + 1. + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::East),
-                                normal,
-                            },
-                        ]);
-                    }
+        mesh
+    }
 
-                    if x == 0 || self.get_block(x - 1, y, z).is_transparent() {
-                        let normal = [-1.0, 0.0, 0.0];
-
-                        vertices.extend_from_slice(&[
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + 1.0 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                            Vertex {
-                                position: [
-                                    x as f32 + self.position.x,
-                                    y as f32 + 1.0 + self.position.y,
-                                    z as f32 + self.position.z,
-                                ],
-                                color: block.get_face_color(BlockFace::West),
-                                normal,
-                            },
-                        ]);
-                    }
-                }
+    fn block_height(
+        &self,
+        neighbors: &ChunkNeighbors,
+        block: BlockType,
+        x: usize,
+        y: usize,
+        z: usize,
+    ) -> f32 {
+        let above = self.block_at(neighbors, [x as i32, y as i32 + 1, z as i32]);
+        if !block.is_liquid() || above.is_none_or(|above| above.is_liquid()) {
+            return 1.0;
+        }
+        match block {
+            // Shallower the further it has flowed, down to an eighth of a source.
+            BlockType::FlowingWater(level) => {
+                WATER_SURFACE_HEIGHT * level as f32 / (MAX_FLOW_LEVEL + 1) as f32
             }
+            _ => WATER_SURFACE_HEIGHT,
         }
+    }
 
-        vertices
+    fn is_face_visible(
+        &self,
+        neighbors: &ChunkNeighbors,
+        block: BlockType,
+        x: usize,
+        y: usize,
+        z: usize,
+        offset: [i32; 3],
+    ) -> bool {
+        let neighbor = [
+            x as i32 + offset[0],
+            y as i32 + offset[1],
+            z as i32 + offset[2],
+        ];
+
+        self.block_at(neighbors, neighbor)
+            .is_none_or(|neighbor| block.is_face_visible_against(neighbor))
     }
 
-    fn generate_tree(&mut self, x: usize, y: usize, z: usize) {
-        let height = rand::thread_rng().gen_range(4..7);
+    /// Looks up a block up to one step outside this chunk, or `None` if it
+    /// falls in a neighbor that isn't loaded.
+    fn block_at(&self, neighbors: &ChunkNeighbors, [x, y, z]: [i32; 3]) -> Option<BlockType> {
+        let size = CHUNK_SIZE as i32;
+        let offset = [x.div_euclid(size), y.div_euclid(size), z.div_euclid(size)];
+        if offset == [0, 0, 0] {
+            return Some(self.get_block(x as usize, y as usize, z as usize));
+        }
+
+        let index = FACES.iter().position(|face| face.offset == offset)?;
+        let chunk = neighbors.0[index].as_ref()?;
+        Some(chunk.get_block(
+            x.rem_euclid(size) as usize,
+            y.rem_euclid(size) as usize,
+            z.rem_euclid(size) as usize,
+        ))
+    }
+
+    fn push_face(
+        &self,
+        vertices: &mut Vec<Vertex>,
+        block: BlockType,
+        [x, y, z]: [usize; 3],
+        height: f32,
+        face: &FaceGeometry,
+        textures: &BlockTextures,
+    ) {
+        let texture = textures.layer(block.texture_name(face.face));
+        let [r, g, b] = match texture {
+            Some(_) => [face.face.shade(); 3],
+            None => block.get_face_color(face.face),
+        };
+        let color = [r, g, b, block.opacity()];
+
+        for corner in face.corners {
+            vertices.push(Vertex {
+                position: [
+                    x as f32 + corner[0] + self.position.x,
+                    y as f32 + corner[1] * height + self.position.y,
+                    z as f32 + corner[2] + self.position.z,
+                ],
+                color,
+                normal: face.normal,
+                uv: face_uv(face.face, corner),
+                texture: texture.unwrap_or(-1),
+            });
+        }
+    }
+
+    fn generate_tree(&mut self, rng: &mut StdRng, x: usize, y: usize, z: usize) {
+        let height = rng.gen_range(4..7);
 
         if y + height + 2 >= CHUNK_SIZE {
             return;
         }
 
-        if x < 2 || x >= CHUNK_SIZE - 2 || z < 2 || z >= CHUNK_SIZE - 2 {
+        let trunk_range = 2..CHUNK_SIZE - 2;
+        if !trunk_range.contains(&x) || !trunk_range.contains(&z) {
             return;
         }
 
@@ -485,37 +426,31 @@ impl Chunk {
                     let ny = (leave_start + dy) as i32;
                     let nz = z as i32 + dz;
 
-                    if nx >= 0
-                        && nx < CHUNK_SIZE as i32
-                        && ny >= 0
-                        && ny < CHUNK_SIZE as i32
-                        && nz >= 0
-                        && nz < CHUNK_SIZE as i32
+                    let range = 0..CHUNK_SIZE as i32;
+                    if range.contains(&nx)
+                        && range.contains(&ny)
+                        && range.contains(&nz)
+                        && !(dx == 1 && dz == 0 && dy < height)
                     {
-                        if !(dx == 1 && dz == 0 && dy < height) {
-                            self.set_block(
-                                nx as usize,
-                                ny as usize,
-                                nz as usize,
-                                BlockType::Leaves,
-                            );
-                        }
+                        self.set_block(nx as usize, ny as usize, nz as usize, BlockType::Leaves);
                     }
                 }
             }
         }
     }
 
+    // Seeded from the chunk position so the same chunk always grows the same
+    // trees, which keeps headless golden images stable between runs.
     fn generate_features(&mut self) {
+        let pos = ChunkPos::from_world_pos(self.position);
+        let seed = position_seed(IVec3::new(pos.x, pos.y, pos.z));
+        let mut rng = StdRng::seed_from_u64(seed);
+
         for x in 0..CHUNK_SIZE {
             for z in 0..CHUNK_SIZE {
                 for y in 0..CHUNK_SIZE {
-                    if self.get_block(x, y, z) == BlockType::Grass {
-                        let mut rng = thread_rng();
-
-                        if rng.gen::<f32>() < 0.01 {
-                            self.generate_tree(x, y + 1, z);
-                        }
+                    if self.get_block(x, y, z) == BlockType::Grass && rng.gen::<f32>() < 0.01 {
+                        self.generate_tree(&mut rng, x, y + 1, z);
                     }
                 }
             }
@@ -561,25 +496,6 @@ impl Chunk {
                             BlockType::Grass
                         } else if abs_y <= base_height {
                             BlockType::Dirt
-                        } else if abs_y <= base_height {
-                            if abs_y > base_height - 5 {
-                                BlockType::Sand
-                            } else {
-                                let ore_noise = perlin.get([
-                                    (wx as f64 * 0.5) + abs_y as f64 * 0.1,
-                                    (wz as f64 * 0.5) + abs_y as f64 * 0.1,
-                                ]);
-
-                                if ore_noise > 0.8 && abs_y < 20 {
-                                    BlockType::DiamondOre
-                                } else if ore_noise > 0.7 && abs_y < 40 {
-                                    BlockType::IronOre
-                                } else if ore_noise > 0.6 {
-                                    BlockType::CoalOre
-                                } else {
-                                    BlockType::Stone
-                                }
-                            }
                         } else {
                             BlockType::Stone
                         };
@@ -599,13 +515,36 @@ impl Chunk {
         }
 
         self.generate_features();
+        // Generated terrain can always be regenerated, so there's nothing to save.
+        self.dirty = false;
     }
+}
 
-    pub fn get_chunk_pos(world_pos: Vec3) -> Vec3 {
-        Vec3::new(
-            (world_pos.x / CHUNK_SIZE as f32).floor(),
-            (world_pos.y / CHUNK_SIZE as f32).floor(),
-            (world_pos.z / CHUNK_SIZE as f32).floor(),
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::entity::EntityKind;
+
+    #[test]
+    fn entities_only_dirty_the_chunk_when_they_come_or_go() {
+        let mut chunk = Chunk::new(Vec3::ZERO);
+        chunk.add_entity(Entity::new(EntityKind::Projectile, Vec3::ONE, Vec3::ZERO));
+        chunk.add_entity(Entity::new(EntityKind::Projectile, Vec3::ONE, Vec3::ZERO));
+        assert!(chunk.is_dirty());
+        chunk.mark_saved();
+
+        let mut entities = chunk.take_entities();
+        for entity in &mut entities {
+            entity.position += Vec3::X;
+        }
+        chunk.return_entities(entities, 2);
+        assert!(!chunk.is_dirty());
+        assert_eq!(chunk.entities()[0].position, Vec3::new(2.0, 1.0, 1.0));
+
+        let mut entities = chunk.take_entities();
+        entities.pop();
+        chunk.return_entities(entities, 2);
+        assert!(chunk.is_dirty());
+        assert_eq!(chunk.entities().len(), 1);
     }
 }
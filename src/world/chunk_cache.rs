@@ -0,0 +1,163 @@
+use crate::world::chunk::{Chunk, ChunkPos};
+use crate::world::storage::{ChunkStorage, StorageError};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+pub const DEFAULT_CACHE_BUDGET: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Dirty chunks written to storage on their way out of the cache.
+    pub saves: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+struct CacheEntry {
+    chunk: Chunk,
+    last_used: u64,
+    // What the chunk counts against the budget, fixed when it's inserted.
+    bytes: usize,
+}
+
+/// Recently unloaded chunks, kept in memory up to `budget` bytes. The least
+/// recently used chunk is evicted first, and saved beforehand if it's dirty.
+pub struct ChunkCache {
+    entries: HashMap<ChunkPos, CacheEntry>,
+    // `last_used` -> position, so the oldest entry is always first.
+    by_age: BTreeMap<u64, ChunkPos>,
+    next_use: u64,
+    budget: usize,
+    // Bytes held by the cached chunks, entity lists included.
+    memory_used: usize,
+    storage: Arc<ChunkStorage>,
+    stats: CacheStats,
+}
+
+impl ChunkCache {
+    pub fn new(budget: usize, storage: Arc<ChunkStorage>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            by_age: BTreeMap::new(),
+            next_use: 0,
+            budget,
+            memory_used: 0,
+            storage,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn insert(&mut self, pos: ChunkPos, chunk: Chunk) {
+        self.remove(pos);
+
+        let last_used = self.next_use;
+        self.next_use += 1;
+        let bytes = chunk.memory_size();
+        self.memory_used += bytes;
+        self.entries.insert(
+            pos,
+            CacheEntry {
+                chunk,
+                last_used,
+                bytes,
+            },
+        );
+        self.by_age.insert(last_used, pos);
+
+        while self.memory_used > self.budget {
+            self.evict_oldest();
+        }
+    }
+
+    /// Takes the chunk at `pos` out of the cache, counting a hit or a miss.
+    pub fn take(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        let chunk = self.remove(pos);
+        if chunk.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        chunk
+    }
+
+    fn remove(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        let entry = self.entries.remove(&pos)?;
+        self.by_age.remove(&entry.last_used);
+        self.memory_used -= entry.bytes;
+        Some(entry.chunk)
+    }
+
+    fn evict_oldest(&mut self) {
+        let Some((_, pos)) = self.by_age.pop_first() else {
+            return;
+        };
+        let Some(mut entry) = self.entries.remove(&pos) else {
+            return;
+        };
+        self.memory_used -= entry.bytes;
+
+        self.stats.evictions += 1;
+        if entry.chunk.is_dirty() {
+            match self.storage.save(pos, &mut entry.chunk) {
+                Ok(()) => self.stats.saves += 1,
+                Err(e) => eprintln!("Failed to save chunk {:?}: {}", pos, e),
+            }
+        }
+    }
+
+    /// Saves every dirty chunk still in the cache, e.g. before exiting.
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        for (pos, entry) in &mut self.entries {
+            if entry.chunk.is_dirty() {
+                self.storage.save(*pos, &mut entry.chunk)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::entity::{Entity, EntityKind};
+    use glam::Vec3;
+
+    #[test]
+    fn entity_lists_count_against_the_budget() {
+        let mut crowded = Chunk::new(Vec3::ZERO);
+        for _ in 0..100 {
+            crowded.add_entity(Entity::new(EntityKind::Projectile, Vec3::ONE, Vec3::ZERO));
+        }
+        crowded.mark_saved();
+
+        // Room for the crowded chunk, but not for a bare one next to it.
+        let budget = crowded.memory_size() + std::mem::size_of::<Chunk>() / 2;
+        let storage = Arc::new(ChunkStorage::new(std::env::temp_dir().join("voxel-unused")));
+        let mut cache = ChunkCache::new(budget, storage);
+        let bare = ChunkPos::new(0, 0, 0);
+        let full = ChunkPos::new(1, 0, 0);
+        cache.insert(bare, Chunk::new(bare.to_world_pos()));
+        cache.insert(full, crowded);
+
+        assert_eq!(cache.stats().evictions, 1);
+        assert!(cache.take(bare).is_none());
+        assert!(cache.take(full).is_some());
+        assert_eq!(cache.memory_used, 0);
+    }
+}
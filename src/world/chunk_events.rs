@@ -0,0 +1,46 @@
+use crate::world::block::BlockType;
+use crate::world::chunk::ChunkPos;
+use crossbeam::channel::{self, Receiver, Sender};
+use glam::IVec3;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+    /// World block coordinates.
+    pub position: IVec3,
+    pub old: BlockType,
+    pub new: BlockType,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChunkEvent {
+    /// Freshly generated terrain was inserted.
+    Generated(ChunkPos),
+    /// The chunk was restored from the cache or from storage.
+    Loaded(ChunkPos),
+    Modified {
+        pos: ChunkPos,
+        changes: Arc<[BlockChange]>,
+    },
+    Unloaded(ChunkPos),
+}
+
+/// Hands every chunk event to each subscriber's channel. Subscribers that
+/// dropped their receiver are forgotten on the next publish.
+#[derive(Default)]
+pub struct ChunkEvents {
+    subscribers: Vec<Sender<ChunkEvent>>,
+}
+
+impl ChunkEvents {
+    pub fn subscribe(&mut self) -> Receiver<ChunkEvent> {
+        let (sender, receiver) = channel::unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn publish(&mut self, event: ChunkEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
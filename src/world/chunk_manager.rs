@@ -1,90 +1,349 @@
-use crate::world::chunk::{Chunk, ChunkPos};
-use crate::world::chunk_worker::ChunkWorkerPool;
-use glam::Vec3;
-use parking_lot::Mutex;
+use crate::engine::texture::BlockTextures;
+use crate::utils::frustum::Frustum;
+use crate::world::block::{BlockType, FACE_NEIGHBORS};
+use crate::world::chunk::{Chunk, ChunkMesh, ChunkPos, CHUNK_SIZE};
+use crate::world::chunk_cache::{CacheStats, ChunkCache};
+use crate::world::chunk_events::{BlockChange, ChunkEvent, ChunkEvents};
+use crate::world::chunk_worker::{ChunkResult, ChunkWorkerPool, TaskPriority};
+use crate::world::storage::{ChunkStorage, StorageError, SAVE_DIR};
+use crossbeam::channel::Receiver;
+use glam::{IVec3, Vec3};
+use parking_lot::{Mutex, MutexGuard};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+/// How far around the camera chunks are kept loaded, in chunks. The loaded
+/// region is a cylinder: `horizontal` is its radius, `vertical` how far it
+/// reaches up and down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewDistance {
+    pub horizontal: i32,
+    pub vertical: i32,
+    /// Chunks are only unloaded once they're this many chunks past the view
+    /// distance, so walking back and forth over a border doesn't thrash them.
+    pub unload_margin: i32,
+}
+
+impl ViewDistance {
+    pub fn clamped(self) -> Self {
+        Self {
+            horizontal: self.horizontal.clamp(1, 32),
+            vertical: self.vertical.clamp(1, 16),
+            unload_margin: self.unload_margin.clamp(0, 8),
+        }
+    }
+
+    fn contains(horizontal: i32, vertical: i32, center: ChunkPos, pos: ChunkPos) -> bool {
+        let dx = pos.x - center.x;
+        let dz = pos.z - center.z;
+        dx * dx + dz * dz <= horizontal * horizontal && (pos.y - center.y).abs() <= vertical
+    }
+
+    pub fn in_view(&self, center: ChunkPos, pos: ChunkPos) -> bool {
+        Self::contains(self.horizontal, self.vertical, center, pos)
+    }
+
+    /// Whether a loaded chunk at `pos` should stay loaded.
+    pub fn in_unload_range(&self, center: ChunkPos, pos: ChunkPos) -> bool {
+        Self::contains(
+            self.horizontal + self.unload_margin,
+            self.vertical + self.unload_margin,
+            center,
+            pos,
+        )
+    }
+
+    /// Fog and the far plane should end about here, in world units.
+    pub fn world_distance(&self) -> f32 {
+        (self.horizontal * CHUNK_SIZE as i32) as f32
+    }
+}
+
+impl Default for ViewDistance {
+    fn default() -> Self {
+        Self {
+            horizontal: 6,
+            vertical: 2,
+            unload_margin: 2,
+        }
+    }
+}
+
+/// Owns every loaded chunk and decides which ones should be loaded. Chunks
+/// that leave the view distance go into a memory-bounded cache and are
+/// restored from it, or from storage, before anything is regenerated.
 pub struct ChunkManager {
     chunks: Arc<Mutex<HashMap<ChunkPos, Chunk>>>,
-    chunk_cache: HashMap<ChunkPos, Chunk>,
-    active_chunks: HashSet<ChunkPos>,
+    chunk_cache: ChunkCache,
+    storage: Arc<ChunkStorage>,
     worker_pool: ChunkWorkerPool,
-    cache_size: usize,
+    view_distance: ViewDistance,
+    center: Option<ChunkPos>,
+    finished_meshes: Vec<(ChunkPos, ChunkMesh)>,
+    events: ChunkEvents,
 }
 
 impl ChunkManager {
-    pub fn new() -> Self {
+    pub fn new(
+        view_distance: ViewDistance,
+        dispatch_budget: usize,
+        cache_budget: usize,
+        textures: Arc<BlockTextures>,
+    ) -> Self {
         let chunks = Arc::new(Mutex::new(HashMap::new()));
-        let worker_pool = ChunkWorkerPool::new(Arc::clone(&chunks));
+        let storage = Arc::new(ChunkStorage::new(SAVE_DIR));
+        let worker_pool = ChunkWorkerPool::new(
+            Arc::clone(&chunks),
+            Arc::clone(&storage),
+            textures,
+            dispatch_budget,
+        );
 
         Self {
             chunks,
-            chunk_cache: HashMap::with_capacity(64),
-            active_chunks: HashSet::new(),
+            chunk_cache: ChunkCache::new(cache_budget, Arc::clone(&storage)),
+            storage,
             worker_pool,
-            cache_size: 64,
+            view_distance: view_distance.clamped(),
+            center: None,
+            finished_meshes: Vec::new(),
+            events: ChunkEvents::default(),
         }
     }
 
-    pub fn update(&mut self, camera_pos: Vec3, render_distance: i32) {
-        let camera_chunk = ChunkPos::from_world_pos(camera_pos);
-        let mut new_active = HashSet::new();
-        let mut to_load = Vec::new();
+    pub fn loaded_chunks(&self) -> MutexGuard<'_, HashMap<ChunkPos, Chunk>> {
+        self.chunks.lock()
+    }
 
-        for y in -2..=2 {
-            for x in -render_distance..=render_distance {
-                for z in -render_distance..=render_distance {
-                    let pos =
-                        ChunkPos::new(camera_chunk.x + x, camera_chunk.y + y, camera_chunk.z + z);
+    /// Up-to-date meshes that finished since the last call, ready to upload.
+    pub fn take_meshes(&mut self) -> Vec<(ChunkPos, ChunkMesh)> {
+        std::mem::take(&mut self.finished_meshes)
+    }
 
-                    let dist_sq = x * x + y * y * 4 + z * z;
-                    if dist_sq <= render_distance * render_distance {
-                        new_active.insert(pos);
-                        if !self.chunks.lock().contains_key(&pos) {
-                            to_load.push(pos);
-                        }
-                    }
+    /// A channel receiving every `ChunkEvent` from now on.
+    pub fn subscribe(&mut self) -> Receiver<ChunkEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sets blocks by world position and publishes one `Modified` event for
+    /// each chunk that actually changed. Blocks in chunks that aren't loaded
+    /// are skipped.
+    pub fn set_blocks(
+        &mut self,
+        blocks: impl IntoIterator<Item = (IVec3, BlockType)>,
+    ) -> Vec<BlockChange> {
+        let mut changed: HashMap<ChunkPos, Vec<BlockChange>> = HashMap::new();
+        {
+            let mut chunks = self.chunks.lock();
+            for (position, block) in blocks {
+                let pos = ChunkPos::from_block_pos(position);
+                let Some(chunk) = chunks.get_mut(&pos) else {
+                    continue;
+                };
+                let [x, y, z] = ChunkPos::local_block_pos(position);
+                let old = chunk.get_block(x, y, z);
+                if old != block {
+                    chunk.set_block(x, y, z, block);
+                    changed.entry(pos).or_default().push(BlockChange {
+                        position,
+                        old,
+                        new: block,
+                    });
                 }
             }
         }
 
-        let to_unload: Vec<ChunkPos> = self
-            .active_chunks
-            .difference(&new_active)
-            .copied()
-            .collect();
+        let mut all_changes = Vec::new();
+        for (pos, changes) in changed {
+            all_changes.extend_from_slice(&changes);
+            self.publish(ChunkEvent::Modified {
+                pos,
+                changes: changes.into(),
+            });
+        }
+        all_changes
+    }
 
-        for pos in to_unload {
-            if let Some(chunk) = self.chunks.lock().remove(&pos) {
-                if self.chunk_cache.len() >= self.cache_size {
-                    self.chunk_cache
-                        .remove(&self.chunk_cache.keys().next().copied().unwrap());
-                }
-                self.chunk_cache.insert(pos, chunk);
+    pub fn cache_stats(&self) -> CacheStats {
+        self.chunk_cache.stats()
+    }
+
+    /// Writes every dirty chunk, loaded or cached, to storage.
+    pub fn save_all(&mut self) -> Result<(), StorageError> {
+        for (pos, chunk) in self.chunks.lock().iter_mut() {
+            if chunk.is_dirty() {
+                self.storage.save(*pos, chunk)?;
+            }
+        }
+        self.chunk_cache.flush()
+    }
+
+    pub fn view_distance(&self) -> ViewDistance {
+        self.view_distance
+    }
+
+    /// Takes effect on the next `update`, which loads and unloads chunks to
+    /// match.
+    pub fn set_view_distance(&mut self, view_distance: ViewDistance) {
+        self.view_distance = view_distance.clamped();
+        self.center = None;
+    }
+
+    // Vertical distance counts double when ordering work, since there's
+    // usually more to see sideways than up or down.
+    fn distance_sq(from: ChunkPos, to: ChunkPos) -> i32 {
+        let dx = to.x - from.x;
+        let dy = to.y - from.y;
+        let dz = to.z - from.z;
+        dx * dx + dy * dy * 4 + dz * dz
+    }
+
+    fn priority(camera_chunk: ChunkPos, frustum: &Frustum, pos: ChunkPos) -> TaskPriority {
+        let min = pos.to_world_pos();
+        let max = min + Vec3::splat(CHUNK_SIZE as f32);
+        TaskPriority {
+            outside_frustum: !frustum.is_box_visible(min, max),
+            distance_sq: Self::distance_sq(camera_chunk, pos),
+        }
+    }
+
+    pub fn update(&mut self, camera_pos: Vec3, frustum: &Frustum) {
+        let camera_chunk = ChunkPos::from_world_pos(camera_pos);
+        if self.center != Some(camera_chunk) {
+            self.center = Some(camera_chunk);
+            self.recenter(camera_chunk, frustum);
+        }
+
+        for result in self.worker_pool.poll_results() {
+            match result {
+                ChunkResult::Generated(pos) => self.publish(ChunkEvent::Generated(pos)),
+                ChunkResult::Loaded(pos) => self.publish(ChunkEvent::Loaded(pos)),
+                ChunkResult::Meshed { pos, mesh, .. } => self.finished_meshes.push((pos, mesh)),
             }
         }
 
-        to_load.sort_by_key(|pos| {
-            let dx = pos.x - camera_chunk.x;
-            let dy = pos.y - camera_chunk.y;
-            let dz = pos.z - camera_chunk.z;
-            dx * dx + dy * dy + dz * dz
-        });
+        // The camera turns every frame even when it stays in the same chunk.
+        self.worker_pool
+            .reprioritize(|pos| Self::priority(camera_chunk, frustum, pos));
+        self.worker_pool.process_tasks();
+    }
+
+    /// Keeps meshes in sync with the event, then passes it on to subscribers.
+    fn publish(&mut self, event: ChunkEvent) {
+        match &event {
+            // Faces on the borders shared with neighbors may have appeared
+            // or disappeared too.
+            ChunkEvent::Generated(pos) | ChunkEvent::Loaded(pos) => {
+                self.remesh(std::iter::once(*pos).chain(pos.neighbors()))
+            }
+            ChunkEvent::Modified { pos, changes } => {
+                let touched: HashSet<ChunkPos> = changes
+                    .iter()
+                    .flat_map(|change| {
+                        FACE_NEIGHBORS
+                            .map(|offset| ChunkPos::from_block_pos(change.position + offset))
+                    })
+                    .chain(std::iter::once(*pos))
+                    .collect();
+                self.remesh(touched);
+            }
+            ChunkEvent::Unloaded(pos) => {
+                self.worker_pool.forget_mesh(*pos);
+                // A mesh nobody took yet, e.g. because a frame failed, would
+                // otherwise be uploaded after the unload and never removed.
+                self.finished_meshes.retain(|(finished, _)| finished != pos);
+                self.remesh(pos.neighbors());
+            }
+        }
+        self.events.publish(event);
+    }
+
+    /// Queues new meshes for whichever of `positions` are loaded. Priorities
+    /// only use the distance here; the next `update` accounts for the frustum.
+    fn remesh(&mut self, positions: impl IntoIterator<Item = ChunkPos>) {
+        let loaded: Vec<ChunkPos> = {
+            let chunks = self.chunks.lock();
+            positions
+                .into_iter()
+                .filter(|pos| chunks.contains_key(pos))
+                .collect()
+        };
+
+        for pos in loaded {
+            let priority = TaskPriority {
+                outside_frustum: false,
+                distance_sq: self
+                    .center
+                    .map_or(0, |center| Self::distance_sq(center, pos)),
+            };
+            self.worker_pool.queue_mesh(pos, priority);
+        }
+    }
+
+    fn recenter(&mut self, camera_chunk: ChunkPos, frustum: &Frustum) {
+        for pos in self.unload_distant(camera_chunk) {
+            self.publish(ChunkEvent::Unloaded(pos));
+        }
+
+        let view_distance = self.view_distance;
+        self.worker_pool
+            .cancel_where(|pos| view_distance.in_unload_range(camera_chunk, pos));
+
+        let mut to_load = Vec::new();
+        {
+            let chunks = self.chunks.lock();
+            let ViewDistance {
+                horizontal,
+                vertical,
+                ..
+            } = view_distance;
+            for y in -vertical..=vertical {
+                for x in -horizontal..=horizontal {
+                    for z in -horizontal..=horizontal {
+                        let pos = ChunkPos::new(
+                            camera_chunk.x + x,
+                            camera_chunk.y + y,
+                            camera_chunk.z + z,
+                        );
+
+                        if view_distance.in_view(camera_chunk, pos) && !chunks.contains_key(&pos) {
+                            to_load.push(pos);
+                        }
+                    }
+                }
+            }
+        }
 
         for pos in to_load {
-            if let Some(chunk) = self.chunk_cache.remove(&pos) {
+            // Already on its way; looking it up again would count a cache miss.
+            if self.worker_pool.is_generating(pos) {
+                continue;
+            }
+            if let Some(chunk) = self.chunk_cache.take(pos) {
                 self.chunks.lock().insert(pos, chunk);
+                self.publish(ChunkEvent::Loaded(pos));
             } else {
-                self.worker_pool.queue_chunk_generation(pos);
+                let priority = Self::priority(camera_chunk, frustum, pos);
+                self.worker_pool.queue_chunk_generation(pos, priority);
             }
         }
-
-        self.active_chunks = new_active;
-        self.worker_pool.process_tasks();
     }
 
-    pub fn get_chunks(&self) -> Arc<Mutex<HashMap<ChunkPos, Chunk>>> {
-        Arc::clone(&self.chunks)
+    /// Moves chunks past the unload distance into the cache and returns
+    /// their positions.
+    fn unload_distant(&mut self, camera_chunk: ChunkPos) -> Vec<ChunkPos> {
+        let mut chunks = self.chunks.lock();
+        let to_unload: Vec<ChunkPos> = chunks
+            .keys()
+            .filter(|pos| !self.view_distance.in_unload_range(camera_chunk, **pos))
+            .copied()
+            .collect();
+
+        for pos in &to_unload {
+            if let Some(chunk) = chunks.remove(pos) {
+                self.chunk_cache.insert(*pos, chunk);
+            }
+        }
+        to_unload
     }
 }
@@ -1,68 +1,294 @@
-use crate::world::chunk::{Chunk, ChunkPos};
+use crate::engine::texture::BlockTextures;
+use crate::world::chunk::{Chunk, ChunkMesh, ChunkNeighbors, ChunkPos};
+use crate::world::storage::ChunkStorage;
+use crossbeam::channel::{self, Receiver, Sender};
 use parking_lot::Mutex;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
 
+pub const DEFAULT_DISPATCH_BUDGET: usize = 8;
+const WORKER_THREADS: usize = 4;
+// Only a couple of tasks per thread are handed to rayon at once; the rest
+// wait in our queue where they can still be re-ordered or cancelled.
+const MAX_IN_FLIGHT: usize = WORKER_THREADS * 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ChunkTask {
     Generate(ChunkPos),
+    Mesh(ChunkPos),
+}
+
+impl ChunkTask {
+    pub fn pos(&self) -> ChunkPos {
+        match self {
+            ChunkTask::Generate(pos) | ChunkTask::Mesh(pos) => *pos,
+        }
+    }
+}
+
+/// Sent back from the worker threads once a task has finished.
+pub enum ChunkResult {
+    /// The chunk has been generated and inserted into the shared map.
+    Generated(ChunkPos),
+    /// The chunk was read from storage and inserted into the shared map.
+    Loaded(ChunkPos),
+    Meshed {
+        pos: ChunkPos,
+        version: u64,
+        mesh: ChunkMesh,
+    },
+}
+
+/// Lower runs sooner: anything in view comes before anything outside it,
+/// then nearer chunks before farther ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskPriority {
+    pub outside_frustum: bool,
+    pub distance_sq: i32,
+}
+
+struct QueuedTask {
+    priority: TaskPriority,
+    task: ChunkTask,
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed so the max-heap pops the lowest priority value first.
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
 }
 
 pub struct ChunkWorkerPool {
-    tasks: Arc<Mutex<VecDeque<ChunkTask>>>,
+    queue: BinaryHeap<QueuedTask>,
+    queued: HashSet<ChunkTask>,
     chunks: Arc<Mutex<HashMap<ChunkPos, Chunk>>>,
-    processing: Arc<Mutex<HashSet<ChunkPos>>>,
+    storage: Arc<ChunkStorage>,
+    textures: Arc<BlockTextures>,
+    // Generation tasks that are running, with their cancel flags.
+    in_flight: Arc<Mutex<HashMap<ChunkPos, Arc<AtomicBool>>>>,
+    running: Arc<AtomicUsize>,
+    // The version each chunk's mesh must carry to be current. Versions come
+    // from one counter that only goes up, so a mesh started before a chunk
+    // was unloaded can't match the version it gets after reloading.
+    mesh_versions: HashMap<ChunkPos, u64>,
+    next_mesh_version: u64,
+    result_sender: Sender<ChunkResult>,
+    result_receiver: Receiver<ChunkResult>,
+    dispatch_budget: usize,
     thread_pool: rayon::ThreadPool,
 }
 
 impl ChunkWorkerPool {
-    pub fn new(chunks: Arc<Mutex<HashMap<ChunkPos, Chunk>>>) -> Self {
+    pub fn new(
+        chunks: Arc<Mutex<HashMap<ChunkPos, Chunk>>>,
+        storage: Arc<ChunkStorage>,
+        textures: Arc<BlockTextures>,
+        dispatch_budget: usize,
+    ) -> Self {
         let thread_pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(4)
+            .num_threads(WORKER_THREADS)
             .build()
             .unwrap();
+        let (result_sender, result_receiver) = channel::unbounded();
 
         Self {
-            tasks: Arc::new(Mutex::new(VecDeque::new())),
+            queue: BinaryHeap::new(),
+            queued: HashSet::new(),
             chunks,
-            processing: Arc::new(Mutex::new(HashSet::new())),
+            storage,
+            textures,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            running: Arc::new(AtomicUsize::new(0)),
+            mesh_versions: HashMap::new(),
+            next_mesh_version: 0,
+            result_sender,
+            result_receiver,
+            dispatch_budget: dispatch_budget.max(1),
             thread_pool,
         }
     }
 
-    pub fn queue_chunk_generation(&self, pos: ChunkPos) {
-        let mut processing = self.processing.lock();
-        if processing.contains(&pos) || self.chunks.lock().contains_key(&pos) {
+    /// Whether generating or loading `pos` is queued or running.
+    pub fn is_generating(&self, pos: ChunkPos) -> bool {
+        self.queued.contains(&ChunkTask::Generate(pos)) || self.in_flight.lock().contains_key(&pos)
+    }
+
+    pub fn queue_chunk_generation(&mut self, pos: ChunkPos, priority: TaskPriority) {
+        if self.is_generating(pos) || self.chunks.lock().contains_key(&pos) {
             return;
         }
-        processing.insert(pos);
-        self.tasks.lock().push_back(ChunkTask::Generate(pos));
+        let task = ChunkTask::Generate(pos);
+        self.queued.insert(task);
+        self.queue.push(QueuedTask { priority, task });
+    }
+
+    /// Marks the mesh of `pos` as out of date and queues a rebuild. Results
+    /// from rebuilds that are already running will be discarded.
+    pub fn queue_mesh(&mut self, pos: ChunkPos, priority: TaskPriority) {
+        self.next_mesh_version += 1;
+        self.mesh_versions.insert(pos, self.next_mesh_version);
+
+        let task = ChunkTask::Mesh(pos);
+        if self.queued.insert(task) {
+            self.queue.push(QueuedTask { priority, task });
+        }
     }
 
-    pub fn process_tasks(&self) {
-        let max_tasks = 4;
-        let mut tasks_to_process = Vec::new();
+    /// Drops any pending or running mesh work for a chunk that was unloaded.
+    pub fn forget_mesh(&mut self, pos: ChunkPos) {
+        self.mesh_versions.remove(&pos);
+        if self.queued.remove(&ChunkTask::Mesh(pos)) {
+            self.queue
+                .retain(|queued| queued.task != ChunkTask::Mesh(pos));
+        }
+    }
 
-        {
-            let mut task_queue = self.tasks.lock();
-            while tasks_to_process.len() < max_tasks && !task_queue.is_empty() {
-                if let Some(task) = task_queue.pop_front() {
-                    tasks_to_process.push(task);
+    /// Everything the workers finished since the last call, minus meshes that
+    /// were already out of date.
+    pub fn poll_results(&mut self) -> Vec<ChunkResult> {
+        self.result_receiver
+            .try_iter()
+            .filter(|result| match result {
+                ChunkResult::Generated(_) | ChunkResult::Loaded(_) => true,
+                ChunkResult::Meshed { pos, version, .. } => {
+                    self.mesh_versions.get(pos) == Some(version)
                 }
+            })
+            .collect()
+    }
+
+    /// Recomputes the priority of every queued task, e.g. after the camera
+    /// moved or turned.
+    pub fn reprioritize(&mut self, priority: impl Fn(ChunkPos) -> TaskPriority) {
+        let tasks = std::mem::take(&mut self.queue).into_vec();
+        self.queue = tasks
+            .into_iter()
+            .map(|queued| QueuedTask {
+                priority: priority(queued.task.pos()),
+                task: queued.task,
+            })
+            .collect();
+    }
+
+    /// Drops queued tasks and flags running ones for every chunk `keep`
+    /// rejects. Running tasks finish their current work but discard the result.
+    pub fn cancel_where(&mut self, keep: impl Fn(ChunkPos) -> bool) {
+        self.queue.retain(|queued| keep(queued.task.pos()));
+        self.queued.retain(|task| keep(task.pos()));
+
+        for (pos, cancelled) in self.in_flight.lock().iter() {
+            if !keep(*pos) {
+                cancelled.store(true, AtomicOrdering::Relaxed);
             }
         }
+    }
 
-        for task in tasks_to_process {
-            let chunks = Arc::clone(&self.chunks);
-            let processing = Arc::clone(&self.processing);
+    /// Hands up to `dispatch_budget` of the highest priority tasks to the
+    /// worker threads.
+    pub fn process_tasks(&mut self) {
+        for _ in 0..self.dispatch_budget {
+            if self.running.load(AtomicOrdering::Relaxed) >= MAX_IN_FLIGHT {
+                break;
+            }
+            let Some(QueuedTask { task, .. }) = self.queue.pop() else {
+                break;
+            };
+            self.queued.remove(&task);
 
-            self.thread_pool.spawn(move || match task {
-                ChunkTask::Generate(pos) => {
-                    let mut chunk = Chunk::new(pos.to_world_pos());
-                    chunk.generate_terrain(pos.to_world_pos());
-                    chunks.lock().insert(pos, chunk);
-                    processing.lock().remove(&pos);
+            match task {
+                ChunkTask::Generate(pos) => self.spawn_generation(pos),
+                ChunkTask::Mesh(pos) => {
+                    if let Some(&version) = self.mesh_versions.get(&pos) {
+                        self.spawn_mesh(pos, version);
+                    }
                 }
-            });
+            }
         }
     }
+
+    fn spawn_generation(&self, pos: ChunkPos) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.in_flight.lock().insert(pos, Arc::clone(&cancelled));
+        self.running.fetch_add(1, AtomicOrdering::Relaxed);
+
+        let chunks = Arc::clone(&self.chunks);
+        let storage = Arc::clone(&self.storage);
+        let in_flight = Arc::clone(&self.in_flight);
+        let running = Arc::clone(&self.running);
+        let results = self.result_sender.clone();
+
+        self.thread_pool.spawn(move || {
+            // Chunks that were changed and saved are loaded instead of
+            // generated; a broken save falls back to fresh terrain.
+            let chunk = (!cancelled.load(AtomicOrdering::Relaxed)).then(|| {
+                let saved = storage.load(pos).unwrap_or_else(|e| {
+                    eprintln!("Failed to load chunk {:?}: {}", pos, e);
+                    None
+                });
+                match saved {
+                    Some(chunk) => (chunk, ChunkResult::Loaded(pos)),
+                    None => {
+                        let mut chunk = Chunk::new(pos.to_world_pos());
+                        chunk.generate_terrain(pos.to_world_pos());
+                        (chunk, ChunkResult::Generated(pos))
+                    }
+                }
+            });
+
+            // Checked again under the lock `cancel_where` holds, so a chunk
+            // cancelled mid-generation never gets inserted.
+            let mut in_flight = in_flight.lock();
+            if let Some((chunk, result)) =
+                chunk.filter(|_| !cancelled.load(AtomicOrdering::Relaxed))
+            {
+                chunks.lock().insert(pos, chunk);
+                let _ = results.send(result);
+            }
+            in_flight.remove(&pos);
+            running.fetch_sub(1, AtomicOrdering::Relaxed);
+        });
+    }
+
+    fn spawn_mesh(&self, pos: ChunkPos, version: u64) {
+        self.running.fetch_add(1, AtomicOrdering::Relaxed);
+
+        let chunks = Arc::clone(&self.chunks);
+        let textures = Arc::clone(&self.textures);
+        let running = Arc::clone(&self.running);
+        let results = self.result_sender.clone();
+
+        self.thread_pool.spawn(move || {
+            // Mesh from copies so the map isn't locked while meshing.
+            let snapshot = {
+                let chunks = chunks.lock();
+                chunks.get(&pos).cloned().map(|chunk| {
+                    let neighbors = pos.neighbors().map(|pos| chunks.get(&pos).cloned());
+                    (chunk, ChunkNeighbors::new(neighbors))
+                })
+            };
+
+            if let Some((chunk, neighbors)) = snapshot {
+                let mesh = chunk.generate_mesh(&neighbors, &textures);
+                let _ = results.send(ChunkResult::Meshed { pos, version, mesh });
+            }
+            running.fetch_sub(1, AtomicOrdering::Relaxed);
+        });
+    }
 }
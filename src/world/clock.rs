@@ -0,0 +1,146 @@
+use crate::engine::light::Light;
+use crate::utils::math::smoothstep;
+use glam::Vec3;
+use std::f32::consts::TAU;
+
+pub const DEFAULT_DAY_LENGTH: f32 = 600.0;
+
+pub const MIDNIGHT: f32 = 0.0;
+pub const SUNRISE: f32 = 0.25;
+pub const NOON: f32 = 0.5;
+
+const SUN_NOON_COLOR: Vec3 = Vec3::new(1.0, 0.97, 0.9);
+const SUN_HORIZON_COLOR: Vec3 = Vec3::new(1.0, 0.55, 0.3);
+const MOON_COLOR: Vec3 = Vec3::new(0.18, 0.22, 0.35);
+const DAY_AMBIENT: Vec3 = Vec3::new(0.28, 0.3, 0.34);
+const NIGHT_AMBIENT: Vec3 = Vec3::new(0.03, 0.04, 0.07);
+
+/// Time of day as a fraction of a full day: 0.0 is midnight, 0.25 sunrise,
+/// 0.5 noon and 0.75 sunset.
+pub struct WorldClock {
+    time_of_day: f32,
+    day: u32,
+    day_length: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl WorldClock {
+    pub fn new(day_length: f32) -> Self {
+        Self {
+            time_of_day: SUNRISE + 0.05,
+            day: 0,
+            day_length: day_length.max(1.0),
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        if !self.paused {
+            self.advance(dt * self.speed / self.day_length);
+        }
+    }
+
+    fn advance(&mut self, days: f32) {
+        let time = self.time_of_day + days;
+        self.day += time.floor() as u32;
+        self.time_of_day = time.rem_euclid(1.0);
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    /// Whole days that have passed since the clock started.
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Multiplier on how fast the day passes; 1.0 is real time.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Skips ahead by `seconds` of game time, even while paused.
+    pub fn fast_forward(&mut self, seconds: f32) {
+        self.advance(seconds.max(0.0) / self.day_length);
+    }
+
+    /// Unit vector pointing at the sun; it rises in +X and sets in -X.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day - SUNRISE) * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.2).normalize()
+    }
+
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    pub fn daylight(&self) -> f32 {
+        daylight(self.sun_direction().y)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.sun_direction().y < -0.05
+    }
+
+    /// Hours and minutes on a 24 hour clock.
+    pub fn hour_minute(&self) -> (u32, u32) {
+        let minutes = (self.time_of_day() * 24.0 * 60.0) as u32;
+        (minutes / 60, minutes % 60)
+    }
+
+    /// Points `light` away from whichever of the sun or moon is up and sets
+    /// its color and ambient level for the current time.
+    pub fn apply_lighting(&self, light: &mut Light) {
+        let sun = self.sun_direction();
+        let daylight = self.daylight();
+
+        if sun.y >= 0.0 {
+            let elevation = smoothstep(0.0, 0.5, sun.y);
+            light.direction = -sun;
+            light.color = SUN_HORIZON_COLOR.lerp(SUN_NOON_COLOR, elevation) * daylight;
+        } else {
+            let moon = self.moon_direction();
+            let moon_light = smoothstep(0.0, 0.3, moon.y);
+            light.direction = -moon;
+            light.color = MOON_COLOR * moon_light;
+        }
+        light.ambient = NIGHT_AMBIENT.lerp(DAY_AMBIENT, daylight);
+        light.update();
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_DAY_LENGTH)
+    }
+}
+
+/// 1.0 while the sun is well above the horizon, 0.0 once it has set, given
+/// the height of the unit vector towards the sun. Shared by the sky and the
+/// scene lighting so the two stay in step.
+pub fn daylight(sun_height: f32) -> f32 {
+    smoothstep(-0.1, 0.2, sun_height)
+}
@@ -0,0 +1,225 @@
+use crate::engine::renderer::Vertex;
+use crate::utils::aabb::Aabb;
+use crate::world::block::BlockType;
+use crate::world::chunk::{push_box, ChunkPos};
+use crate::world::mob::{Behavior, Navigation};
+use crate::world::physics::{self, GRAVITY, TERMINAL_VELOCITY};
+use glam::{IVec3, Vec3};
+
+// Accelerations in blocks per second squared.
+const PROJECTILE_GRAVITY: f32 = 12.0;
+/// Blocks per second.
+const MOB_SPEED: f32 = 4.0;
+const MOB_ACCELERATION: f32 = 30.0;
+/// Enough to clear a block, or a one-block gap at a run.
+const MOB_JUMP_VELOCITY: f32 = 8.5;
+/// Mobs float with about this much of them under water.
+const MOB_FLOAT_DEPTH: f32 = 0.5;
+const SWIM_ACCELERATION: f32 = 40.0;
+/// Fraction of horizontal speed lost per second while on the ground.
+const GROUND_FRICTION: f32 = 8.0;
+/// Fraction of speed lost per second in water.
+const WATER_DRAG: f32 = 3.0;
+/// Seconds before a dropped item disappears.
+const ITEM_LIFETIME: f32 = 300.0;
+/// Seconds before a projectile disappears, whether or not it hit anything.
+const PROJECTILE_LIFETIME: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Mob(Behavior),
+    /// A dropped block.
+    Item(BlockType),
+    /// Flies until it hits something, then stays stuck there.
+    Projectile,
+    /// A block falling until it lands, where it's placed again.
+    FallingBlock(BlockType),
+}
+
+impl EntityKind {
+    /// Stable ids used in saved chunks, along with the item's block id.
+    pub fn id(&self) -> (u8, u8) {
+        match self {
+            EntityKind::Mob(behavior) => (0, behavior.id()),
+            EntityKind::Item(block) => (1, block.id()),
+            EntityKind::Projectile => (2, 0),
+            EntityKind::FallingBlock(block) => (3, block.id()),
+        }
+    }
+
+    pub fn from_id(id: u8, data: u8) -> Option<Self> {
+        Some(match id {
+            0 => EntityKind::Mob(Behavior::from_id(data)?),
+            1 => EntityKind::Item(BlockType::from_id(data)?),
+            2 => EntityKind::Projectile,
+            3 => EntityKind::FallingBlock(BlockType::from_id(data)?),
+            _ => return None,
+        })
+    }
+
+    /// Width, height and depth of the entity's box.
+    pub fn size(&self) -> Vec3 {
+        match self {
+            EntityKind::Mob(_) => Vec3::new(0.8, 0.9, 0.8),
+            EntityKind::Item(_) => Vec3::splat(0.25),
+            EntityKind::Projectile => Vec3::splat(0.2),
+            // Just under a block, so it drops down a one-block hole.
+            EntityKind::FallingBlock(_) => Vec3::splat(0.98),
+        }
+    }
+
+    fn color(&self) -> [f32; 3] {
+        match self {
+            EntityKind::Mob(Behavior::Wander) => [0.9, 0.6, 0.6],
+            EntityKind::Mob(Behavior::Follow) => [0.5, 0.8, 0.5],
+            EntityKind::Mob(Behavior::Flee) => [0.6, 0.6, 0.9],
+            EntityKind::Item(block) | EntityKind::FallingBlock(block) => block.get_color(),
+            EntityKind::Projectile => [0.3, 0.3, 0.3],
+        }
+    }
+
+    fn gravity(&self) -> f32 {
+        match self {
+            EntityKind::Projectile => PROJECTILE_GRAVITY,
+            _ => GRAVITY,
+        }
+    }
+
+    fn lifetime(&self) -> Option<f32> {
+        match self {
+            EntityKind::Mob(_) | EntityKind::FallingBlock(_) => None,
+            EntityKind::Item(_) => Some(ITEM_LIFETIME),
+            EntityKind::Projectile => Some(PROJECTILE_LIFETIME),
+        }
+    }
+}
+
+/// Anything in the world besides blocks. Entities live in the chunk they're
+/// in, and are saved and unloaded with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub kind: EntityKind,
+    /// Center of the bottom of the entity's box.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Seconds since the entity was spawned.
+    pub age: f32,
+    /// Where a mob is going; unused by other kinds.
+    pub navigation: Navigation,
+    /// Where the entity was before the last tick, for interpolation.
+    previous_position: Vec3,
+    on_ground: bool,
+}
+
+impl Entity {
+    pub fn new(kind: EntityKind, position: Vec3, velocity: Vec3) -> Self {
+        Self {
+            kind,
+            position,
+            velocity,
+            age: 0.0,
+            navigation: Navigation::default(),
+            previous_position: position,
+            on_ground: false,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds_at(self.position)
+    }
+
+    fn bounds_at(&self, position: Vec3) -> Aabb {
+        let size = self.kind.size();
+        let half = Vec3::new(size.x * 0.5, 0.0, size.z * 0.5);
+        Aabb::new(position - half, position + half + Vec3::Y * size.y)
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos::from_world_pos(self.position)
+    }
+
+    /// The block a falling block that has come to rest should become, and
+    /// where.
+    pub fn landed(&self) -> Option<(IVec3, BlockType)> {
+        match self.kind {
+            EntityKind::FallingBlock(block) if self.on_ground => {
+                Some(((self.position + Vec3::Y * 0.5).floor().as_ivec3(), block))
+            }
+            _ => None,
+        }
+    }
+
+    /// Advances the entity by `dt` seconds against the blocks `block_at`
+    /// reports. Returns false once the entity should be removed.
+    pub fn tick(&mut self, dt: f32, block_at: impl Fn(IVec3) -> Option<BlockType>) -> bool {
+        self.previous_position = self.position;
+        self.age += dt;
+        if self
+            .kind
+            .lifetime()
+            .is_some_and(|lifetime| self.age > lifetime)
+        {
+            return false;
+        }
+        if self.kind == EntityKind::Projectile && self.velocity == Vec3::ZERO {
+            return true;
+        }
+
+        let submersion = physics::submersion(&self.bounds(), &block_at);
+        self.velocity.y = (self.velocity.y - self.kind.gravity() * dt).max(-TERMINAL_VELOCITY);
+        if submersion > 0.0 {
+            self.velocity *= (1.0 - WATER_DRAG * dt).max(0.0);
+        }
+        if let EntityKind::Mob(_) = self.kind {
+            self.walk(submersion, dt);
+        } else if self.on_ground {
+            let friction = (1.0 - GROUND_FRICTION * dt).max(0.0);
+            self.velocity.x *= friction;
+            self.velocity.z *= friction;
+        }
+
+        let sweep = physics::sweep(&self.bounds(), self.velocity * dt, &block_at);
+        self.position += sweep.offset;
+        self.on_ground = sweep.blocked[1] && self.velocity.y <= 0.0;
+        if self.kind == EntityKind::Projectile && sweep.blocked.contains(&true) {
+            self.velocity = Vec3::ZERO;
+        }
+        for axis in 0..3 {
+            if sweep.blocked[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        true
+    }
+
+    /// Moves a mob the way its navigation wants to go.
+    fn walk(&mut self, submersion: f32, dt: f32) {
+        let target = self.navigation.walk * MOB_SPEED;
+        let horizontal = Vec3::new(self.velocity.x, 0.0, self.velocity.z);
+        let change = (target - horizontal).clamp_length_max(MOB_ACCELERATION * dt);
+        self.velocity.x += change.x;
+        self.velocity.z += change.z;
+
+        // Swimming up doubles as jumping, to climb out onto a bank.
+        if submersion > MOB_FLOAT_DEPTH || (submersion > 0.0 && self.navigation.jump) {
+            self.velocity.y += SWIM_ACCELERATION * dt;
+        } else if self.navigation.jump && self.on_ground {
+            self.velocity.y = MOB_JUMP_VELOCITY;
+        }
+    }
+}
+
+/// Flat colored boxes for `entities`, placed `alpha` of the way from where
+/// each was before its last tick to where it is now.
+pub fn mesh_entities(entities: &[Entity], alpha: f32) -> Vec<Vertex> {
+    let mut vertices = Vec::new();
+    for entity in entities {
+        let position = entity.previous_position.lerp(entity.position, alpha);
+        push_box(
+            &mut vertices,
+            &entity.bounds_at(position),
+            entity.kind.color(),
+        );
+    }
+    vertices
+}
@@ -0,0 +1,63 @@
+use crate::world::block::{BlockType, FACE_NEIGHBORS, MAX_FIRE_AGE};
+use glam::IVec3;
+use rand::Rng;
+
+/// Ticks between a fire's updates.
+pub const FIRE_DELAY: u64 = 15;
+
+/// The blocks a fire at `position` of `age` changes in one update: flammable
+/// neighbors it burns away, air nearby it spreads to, and the fire itself,
+/// which ages and goes out after `MAX_FIRE_AGE` updates or next to liquid.
+pub fn burn(
+    position: IVec3,
+    age: u8,
+    rng: &mut impl Rng,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) -> Vec<(IVec3, BlockType)> {
+    let neighbors = FACE_NEIGHBORS.map(|offset| (position + offset, block_at(position + offset)));
+    if neighbors
+        .iter()
+        .any(|(_, block)| block.is_some_and(|block| block.is_liquid()))
+    {
+        return vec![(position, BlockType::Air)];
+    }
+
+    let mut changes = Vec::new();
+    for (neighbor, block) in neighbors {
+        let Some(block) = block.filter(|block| block.is_flammable()) else {
+            continue;
+        };
+        let chance = FIRE_DELAY as f32 / block.burn_time().max(1) as f32;
+        if rng.gen::<f32>() < chance {
+            changes.push((neighbor, block.burned()));
+        }
+    }
+
+    // Any air around the fire catches from the most flammable block next to it.
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let target = position + IVec3::new(x, y, z);
+                if target == position || block_at(target) != Some(BlockType::Air) {
+                    continue;
+                }
+                let flammability = FACE_NEIGHBORS
+                    .iter()
+                    .filter_map(|&offset| block_at(target + offset))
+                    .map(|block| block.flammability())
+                    .fold(0.0, f32::max);
+                if flammability > 0.0 && rng.gen::<f32>() < flammability {
+                    changes.push((target, BlockType::Fire(0)));
+                }
+            }
+        }
+    }
+
+    let fire = if age >= MAX_FIRE_AGE {
+        BlockType::Air
+    } else {
+        BlockType::Fire(age + 1)
+    };
+    changes.push((position, fire));
+    changes
+}
@@ -0,0 +1,56 @@
+use crate::world::block::{BlockType, MAX_FLOW_LEVEL};
+use glam::IVec3;
+
+/// Ticks water takes to spread one block.
+pub const FLOW_DELAY: u64 = 8;
+const SOURCE_LEVEL: u8 = MAX_FLOW_LEVEL + 1;
+
+const HORIZONTAL: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// How strongly `block` spreads: more than any flowing water for a source,
+/// the level of flowing water, and 0 for anything else.
+fn level(block: BlockType) -> u8 {
+    match block {
+        BlockType::Water => SOURCE_LEVEL,
+        BlockType::FlowingWater(level) => level,
+        _ => 0,
+    }
+}
+
+/// What the air or flowing water at `position` becomes as water flows, or
+/// `None` if it stays as it is. Water below other water is fed at full
+/// strength; otherwise a block is fed one level below its strongest
+/// horizontal neighbor, counting only neighbors resting on something they
+/// can't fall into. Flowing water with nothing feeding it dries up.
+pub fn flow(
+    position: IVec3,
+    block: BlockType,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) -> Option<BlockType> {
+    if !matches!(block, BlockType::Air | BlockType::FlowingWater(_)) {
+        return None;
+    }
+
+    let above = block_at(position + IVec3::Y);
+    let fed = if above.is_some_and(|above| above.is_liquid()) {
+        MAX_FLOW_LEVEL
+    } else {
+        HORIZONTAL
+            .iter()
+            .map(|&offset| position + offset)
+            .filter(|&neighbor| {
+                block_at(neighbor - IVec3::Y)
+                    .is_some_and(|below| below.is_solid() || below == BlockType::Water)
+            })
+            .filter_map(&block_at)
+            .map(|neighbor| level(neighbor).saturating_sub(1))
+            .max()
+            .unwrap_or(0)
+    };
+
+    let new = match fed {
+        0 => BlockType::Air,
+        level => BlockType::FlowingWater(level),
+    };
+    (new != block).then_some(new)
+}
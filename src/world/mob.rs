@@ -0,0 +1,219 @@
+use crate::world::block::BlockType;
+use crate::world::pathfinding::{find_standable, PathResult, Pathfinder};
+use glam::{IVec3, Vec3};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Blocks a mob needs to stand in, from the top of its box rounded up.
+pub const MOB_HEIGHT: i32 = 1;
+const WANDER_RADIUS: i32 = 8;
+const FOLLOW_RANGE: f32 = 24.0;
+/// Followers stop this close to the player.
+const FOLLOW_DISTANCE: f32 = 2.5;
+const FLEE_RANGE: f32 = 10.0;
+/// How far a fleeing mob tries to get from where it is.
+const FLEE_DISTANCE: f32 = 8.0;
+/// Seconds between path requests while chasing a moving goal.
+const REPATH_INTERVAL: f32 = 0.5;
+/// A moving goal only gets a new path once it's this far from the old one.
+const REPATH_DISTANCE: i32 = 2;
+/// Seconds a wandering mob idles between walks.
+const WANDER_PAUSE: std::ops::Range<f32> = 2.0..6.0;
+/// Seconds without getting closer to the next node before giving up on a path.
+const STUCK_TIME: f32 = 1.5;
+/// How close to a node's center counts as having reached it.
+const NODE_RADIUS: f32 = 0.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// Walks to random nearby spots.
+    Wander,
+    /// Walks to the player when they're near, otherwise wanders.
+    Follow,
+    /// Runs from the player when they're near, otherwise wanders.
+    Flee,
+}
+
+impl Behavior {
+    pub fn id(&self) -> u8 {
+        match self {
+            Behavior::Wander => 0,
+            Behavior::Follow => 1,
+            Behavior::Flee => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Behavior::Wander,
+            1 => Behavior::Follow,
+            2 => Behavior::Flee,
+            _ => return None,
+        })
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Behavior::Wander => Behavior::Follow,
+            Behavior::Follow => Behavior::Flee,
+            Behavior::Flee => Behavior::Wander,
+        }
+    }
+}
+
+/// Where a mob is headed and which way it wants to move this tick. Not
+/// saved; a loaded mob just picks a new goal.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Navigation {
+    goal: Option<IVec3>,
+    path: Option<Arc<[IVec3]>>,
+    next: usize,
+    /// Seconds until the mob may ask for a new path.
+    cooldown: f32,
+    closest: f32,
+    stuck: f32,
+    /// Horizontal direction to walk in, zero to stand still.
+    pub walk: Vec3,
+    pub jump: bool,
+}
+
+impl Navigation {
+    fn stop(&mut self) {
+        self.goal = None;
+        self.path = None;
+        self.walk = Vec3::ZERO;
+        self.jump = false;
+    }
+
+    /// Starts along `path` towards `goal`, the spot that was asked for.
+    fn follow(&mut self, goal: IVec3, path: Arc<[IVec3]>) {
+        self.goal = Some(goal);
+        self.path = Some(path);
+        self.next = 0;
+        self.closest = f32::INFINITY;
+        self.stuck = 0.0;
+    }
+
+    /// Points `walk` and `jump` at the next node of the path from `position`,
+    /// the bottom center of the mob. Returns false once there's nothing left
+    /// to follow.
+    fn steer(&mut self, position: Vec3, dt: f32) -> bool {
+        let Some(path) = self.path.clone() else {
+            return false;
+        };
+
+        while let Some(&node) = path.get(self.next) {
+            let target = node.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+            let offset = Vec3::new(target.x - position.x, 0.0, target.z - position.z);
+            let distance = offset.length();
+            if distance < NODE_RADIUS && (target.y - position.y).abs() < 1.0 {
+                self.next += 1;
+                self.closest = f32::INFINITY;
+                self.stuck = 0.0;
+                continue;
+            }
+
+            if distance < self.closest - 0.05 {
+                self.closest = distance;
+                self.stuck = 0.0;
+            } else {
+                self.stuck += dt;
+                if self.stuck > STUCK_TIME {
+                    break;
+                }
+            }
+
+            let feet = position.floor().as_ivec3();
+            let across = (node.x - feet.x).abs().max((node.z - feet.z).abs());
+            self.walk = offset / distance;
+            // Up a block, or over a gap once close to its edge.
+            self.jump = (node.y > feet.y && distance < 1.3) || (across >= 2 && distance < 1.6);
+            return true;
+        }
+
+        self.stop();
+        false
+    }
+}
+
+/// What a mob's decisions depend on besides the mob itself.
+pub struct MobContext<'a> {
+    pub pathfinder: &'a mut Pathfinder,
+    pub rng: &'a mut StdRng,
+    /// Where the player's feet are.
+    pub player: Vec3,
+}
+
+/// Picks a goal for a mob at `position` with `behavior`, asks for a path to
+/// it when needed, and steers `navigation` along it.
+pub fn think(
+    navigation: &mut Navigation,
+    behavior: Behavior,
+    position: Vec3,
+    context: &mut MobContext,
+    dt: f32,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) {
+    navigation.cooldown -= dt;
+    let away = position - context.player;
+    let distance = away.length();
+    let feet = position.floor().as_ivec3();
+
+    let chasing = match behavior {
+        Behavior::Follow if distance < FOLLOW_RANGE => {
+            if distance < FOLLOW_DISTANCE {
+                navigation.stop();
+                return;
+            }
+            Some(context.player.floor().as_ivec3())
+        }
+        Behavior::Flee if distance < FLEE_RANGE => {
+            let direction = Vec3::new(away.x, 0.0, away.z)
+                .try_normalize()
+                .unwrap_or(Vec3::X);
+            Some((position + direction * FLEE_DISTANCE).floor().as_ivec3() + IVec3::Y * 2)
+        }
+        _ => None,
+    };
+
+    let wanted = match chasing {
+        // Only repath when the goal has moved far enough to matter.
+        Some(goal) => {
+            let moved = navigation
+                .goal
+                .is_none_or(|old| (old - goal).abs().max_element() >= REPATH_DISTANCE);
+            (navigation.path.is_none() || moved).then_some(goal)
+        }
+        None if navigation.path.is_none() => {
+            let offset = IVec3::new(
+                context.rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+                3,
+                context.rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+            );
+            Some(feet + offset)
+        }
+        None => None,
+    };
+
+    if let Some(requested) = wanted.filter(|_| navigation.cooldown <= 0.0) {
+        let path = match find_standable(requested, 6, MOB_HEIGHT, &block_at) {
+            Some(goal) => context
+                .pathfinder
+                .find_path(feet, goal, MOB_HEIGHT, &block_at),
+            None => PathResult::NoPath,
+        };
+        match path {
+            PathResult::Found(path) => {
+                navigation.follow(requested, path);
+                navigation.cooldown = REPATH_INTERVAL;
+            }
+            PathResult::NoPath => navigation.cooldown = REPATH_INTERVAL,
+            PathResult::OutOfBudget => {}
+        }
+    }
+
+    if !navigation.steer(position, dt) && chasing.is_none() && navigation.cooldown <= 0.0 {
+        navigation.cooldown = context.rng.gen_range(WANDER_PAUSE);
+    }
+}
@@ -1,4 +1,20 @@
+pub mod access;
 pub mod block;
+pub mod block_updates;
 pub mod chunk;
+pub mod chunk_cache;
+pub mod chunk_events;
 pub mod chunk_manager;
 pub mod chunk_worker;
+pub mod clock;
+pub mod entity;
+pub mod fire;
+pub mod fluid;
+pub mod mob;
+pub mod pathfinding;
+pub mod physics;
+pub mod player;
+pub mod raycast;
+pub mod storage;
+#[cfg(test)]
+pub mod test_layout;
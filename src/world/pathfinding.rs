@@ -0,0 +1,554 @@
+use crate::world::block::BlockType;
+use crate::world::physics::is_solid;
+use glam::IVec3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+/// Nodes all searches together may expand in one tick.
+pub const DEFAULT_PATH_BUDGET: usize = 4000;
+/// Nodes one search may expand before settling for the closest it got.
+const MAX_SEARCH_NODES: usize = 1500;
+/// Furthest a path drops in one step.
+const MAX_DROP: i32 = 3;
+/// Extra cost of a step through liquid, per block.
+const WATER_COST: f32 = 3.0;
+/// Extra cost of jumping up a block or across a gap.
+const JUMP_COST: f32 = 0.5;
+/// Ticks a cached path stays valid without a block changing near it.
+const CACHE_TICKS: u64 = 200;
+const MAX_CACHED_PATHS: usize = 256;
+
+const DIRECTIONS: [IVec3; 8] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(-1, 0, -1),
+];
+
+/// Blocks a body `height` blocks tall can stand in and move through,
+/// looked up through `block_at`. Unloaded blocks are solid.
+struct Terrain<F> {
+    height: i32,
+    block_at: F,
+}
+
+impl<F: Fn(IVec3) -> Option<BlockType>> Terrain<F> {
+    fn is_clear(&self, position: IVec3) -> bool {
+        (0..self.height).all(|dy| !is_solid((self.block_at)(position + IVec3::Y * dy)))
+    }
+
+    fn is_liquid(&self, position: IVec3) -> bool {
+        (self.block_at)(position).is_some_and(|block| block.is_liquid())
+    }
+
+    /// Whether a body fits at `position` with something to stand on, or
+    /// liquid to swim in.
+    fn is_standable(&self, position: IVec3) -> bool {
+        self.is_clear(position)
+            && (is_solid((self.block_at)(position - IVec3::Y)) || self.is_liquid(position))
+    }
+
+    /// Every node reachable from `position` in one move, and what it costs.
+    fn neighbors(&self, position: IVec3) -> Vec<(IVec3, f32)> {
+        let mut neighbors = Vec::new();
+        let headroom = self.is_clear(position + IVec3::Y);
+        for direction in DIRECTIONS {
+            let next = position + direction;
+            let diagonal = direction.x != 0 && direction.z != 0;
+            // Diagonals only where both sides are open, so corners aren't cut.
+            if diagonal
+                && !(self.is_clear(position + IVec3::X * direction.x)
+                    && self.is_clear(position + IVec3::Z * direction.z))
+            {
+                continue;
+            }
+            let distance = if diagonal {
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+
+            if self.is_standable(next) {
+                neighbors.push((next, distance));
+            } else if self.is_clear(next) {
+                // Off an edge: drop to the first floor below, if it's close.
+                let landing = (1..=MAX_DROP)
+                    .map(|drop| next - IVec3::Y * drop)
+                    .take_while(|&below| self.is_clear(below))
+                    .find(|&below| self.is_standable(below));
+                if let Some(landing) = landing {
+                    neighbors.push((landing, distance + (position.y - landing.y) as f32));
+                }
+
+                // Or jump the gap to the block beyond it.
+                let beyond = next + direction;
+                if !diagonal
+                    && headroom
+                    && self.is_clear(next + IVec3::Y)
+                    && self.is_standable(beyond)
+                {
+                    neighbors.push((beyond, 2.0 + JUMP_COST));
+                }
+            } else if !diagonal && headroom && self.is_standable(next + IVec3::Y) {
+                neighbors.push((next + IVec3::Y, distance + JUMP_COST));
+            }
+        }
+
+        for (next, cost) in &mut neighbors {
+            if self.is_liquid(*next) {
+                *cost *= WATER_COST;
+            }
+        }
+        neighbors
+    }
+}
+
+/// The first spot at or below `position`, within `depth` blocks, where a body
+/// `height` blocks tall can stand.
+pub fn find_standable(
+    position: IVec3,
+    depth: i32,
+    height: i32,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) -> Option<IVec3> {
+    let terrain = Terrain { height, block_at };
+    (0..=depth)
+        .map(|drop| position - IVec3::Y * drop)
+        .find(|&below| terrain.is_standable(below))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathResult {
+    /// The nodes to walk through after the start, ending at the goal or, if
+    /// the goal is too far to search, as close to it as the search got.
+    Found(Arc<[IVec3]>),
+    NoPath,
+    /// This tick's budget is spent; ask again next tick.
+    OutOfBudget,
+}
+
+struct CachedPath {
+    path: Option<Arc<[IVec3]>>,
+    tick: u64,
+}
+
+/// A* over the block grid for bodies that walk, step up single blocks,
+/// jump one-block gaps, drop off ledges and swim. Searches share a node
+/// budget per tick, and results are cached until a block near them changes.
+pub struct Pathfinder {
+    budget_per_tick: usize,
+    remaining: usize,
+    tick: u64,
+    // Keyed by start, goal and body height, since a path one body fits
+    // through may be too low for a taller one.
+    cache: HashMap<(IVec3, IVec3, i32), CachedPath>,
+}
+
+impl Pathfinder {
+    pub fn new(budget_per_tick: usize) -> Self {
+        Self {
+            budget_per_tick,
+            remaining: budget_per_tick,
+            tick: 0,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Refills the search budget and expires old cached paths.
+    pub fn begin_tick(&mut self) {
+        self.tick += 1;
+        self.remaining = self.budget_per_tick;
+        let tick = self.tick;
+        self.cache
+            .retain(|_, cached| tick - cached.tick <= CACHE_TICKS);
+    }
+
+    /// Forgets cached paths a change to the block at `position` could affect:
+    /// any passing next to it, and every failed search.
+    pub fn invalidate(&mut self, position: IVec3) {
+        self.cache.retain(|_, cached| match &cached.path {
+            Some(path) => !path
+                .iter()
+                .any(|node| (*node - position).abs().max_element() <= 2),
+            None => false,
+        });
+    }
+
+    /// A path for a body `height` blocks tall from `start` to `goal`, both
+    /// positions of the block its feet are in.
+    pub fn find_path(
+        &mut self,
+        start: IVec3,
+        goal: IVec3,
+        height: i32,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) -> PathResult {
+        if let Some(cached) = self.cache.get(&(start, goal, height)) {
+            return match &cached.path {
+                Some(path) => PathResult::Found(Arc::clone(path)),
+                None => PathResult::NoPath,
+            };
+        }
+        if self.remaining == 0 {
+            return PathResult::OutOfBudget;
+        }
+
+        let limit = self.remaining.min(MAX_SEARCH_NODES);
+        let terrain = Terrain { height, block_at };
+        let (path, expanded) = search(&terrain, start, goal, limit);
+        self.remaining -= expanded;
+        // Cut short by what was left of this tick's budget rather than by the
+        // search limit: try again with a full budget instead of settling for
+        // a partial path.
+        let full_limit = self.budget_per_tick.min(MAX_SEARCH_NODES);
+        if expanded == limit && limit < full_limit && path.last() != Some(&goal) {
+            return PathResult::OutOfBudget;
+        }
+
+        let path: Option<Arc<[IVec3]>> = (!path.is_empty()).then(|| path.into());
+        if self.cache.len() >= MAX_CACHED_PATHS {
+            let tick = self.tick;
+            self.cache.retain(|_, cached| cached.tick == tick);
+        }
+        self.cache.insert(
+            (start, goal, height),
+            CachedPath {
+                path: path.clone(),
+                tick: self.tick,
+            },
+        );
+        match path {
+            Some(path) => PathResult::Found(path),
+            None => PathResult::NoPath,
+        }
+    }
+}
+
+/// A node on the open list, ordered so the heap pops the lowest estimate.
+struct Open {
+    estimate: f32,
+    position: IVec3,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Runs A* for at most `limit` expansions. Returns the path to the goal, or
+/// to the node closest to it if the goal wasn't reached (empty if that's the
+/// start), and how many nodes were expanded.
+fn search<F: Fn(IVec3) -> Option<BlockType>>(
+    terrain: &Terrain<F>,
+    start: IVec3,
+    goal: IVec3,
+    limit: usize,
+) -> (Vec<IVec3>, usize) {
+    let heuristic = |position: IVec3| position.as_vec3().distance(goal.as_vec3());
+    let mut open = BinaryHeap::from([Open {
+        estimate: heuristic(start),
+        position: start,
+    }]);
+    let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+    let mut cost = HashMap::from([(start, 0.0)]);
+    let mut closest = (heuristic(start), start);
+    let mut expanded = 0;
+
+    while let Some(Open { estimate, position }) = open.pop() {
+        let base = cost[&position];
+        // Already expanded through a cheaper route.
+        if base + heuristic(position) < estimate - 1e-4 {
+            continue;
+        }
+        if position == goal {
+            closest = (0.0, goal);
+            break;
+        }
+        if expanded == limit {
+            break;
+        }
+        expanded += 1;
+
+        for (next, step) in terrain.neighbors(position) {
+            let next_cost = base + step;
+            if cost.get(&next).is_some_and(|&known| known <= next_cost) {
+                continue;
+            }
+            cost.insert(next, next_cost);
+            came_from.insert(next, position);
+            let remaining = heuristic(next);
+            if remaining < closest.0 {
+                closest = (remaining, next);
+            }
+            open.push(Open {
+                estimate: next_cost + remaining,
+                position: next,
+            });
+        }
+    }
+
+    let mut path = vec![closest.1];
+    while let Some(&previous) = came_from.get(path.last().unwrap()) {
+        path.push(previous);
+    }
+    path.pop();
+    path.reverse();
+    (path, expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::test_layout::Layout;
+
+    const HEIGHT: i32 = 1;
+
+    /// A 17 by 17 stone floor at y = -1 around the origin with air above it,
+    /// changed by `blocks`. There's nothing to stand on past the floor.
+    fn layout(blocks: &[(IVec3, BlockType)]) -> Layout {
+        let mut layout = Layout::new(IVec3::splat(-8), IVec3::splat(8));
+        layout.fill(
+            IVec3::new(-8, -1, -8),
+            IVec3::new(8, -1, 8),
+            BlockType::Stone,
+        );
+        for &(position, block) in blocks {
+            layout.set(position, block);
+        }
+        layout
+    }
+
+    /// `block` across the whole width of the floor at `x`, for each of `ys`.
+    fn across(
+        x: i32,
+        ys: impl IntoIterator<Item = i32> + Clone,
+        block: BlockType,
+    ) -> Vec<(IVec3, BlockType)> {
+        (-8..=8)
+            .flat_map(|z| {
+                ys.clone()
+                    .into_iter()
+                    .map(move |y| (IVec3::new(x, y, z), block))
+            })
+            .collect()
+    }
+
+    fn path(start: IVec3, goal: IVec3, blocks: &[(IVec3, BlockType)]) -> Vec<IVec3> {
+        let layout = layout(blocks);
+        match Pathfinder::new(DEFAULT_PATH_BUDGET).find_path(start, goal, HEIGHT, layout.block_at())
+        {
+            PathResult::Found(path) => path.to_vec(),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn walks_straight_across_flat_ground() {
+        let goal = IVec3::new(5, 0, 0);
+        let path = path(IVec3::ZERO, goal, &[]);
+        assert_eq!(
+            path,
+            (1..=5).map(|x| IVec3::new(x, 0, 0)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn steps_up_one_block() {
+        let blocks: Vec<_> = (3..=8)
+            .flat_map(|x| across(x, [0], BlockType::Stone))
+            .collect();
+        let goal = IVec3::new(6, 1, 0);
+        let path = path(IVec3::ZERO, goal, &blocks);
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.contains(&IVec3::new(3, 1, 0)));
+    }
+
+    #[test]
+    fn refuses_a_two_block_wall() {
+        let goal = IVec3::new(6, 0, 0);
+        let path = path(IVec3::ZERO, goal, &across(3, [0, 1], BlockType::Stone));
+        assert_ne!(path.last(), Some(&goal));
+        assert!(path.iter().all(|node| node.x < 3));
+    }
+
+    #[test]
+    fn crosses_chunk_borders_at_negative_coordinates() {
+        // A step up at x = -1, the first block of the chunks below zero.
+        let step: Vec<_> = (-8..=-1)
+            .flat_map(|x| across(x, [0], BlockType::Stone))
+            .collect();
+        let goal = IVec3::new(-5, 1, -5);
+        let path = path(IVec3::new(5, 0, 5), goal, &step);
+        assert_eq!(path.last(), Some(&goal));
+        // Up on the step as soon as it crosses into them.
+        assert!(path.iter().any(|node| node.x == -1));
+        assert!(path.iter().all(|node| node.x >= 0 || node.y == 1));
+    }
+
+    #[test]
+    fn jumps_a_one_block_gap_only_with_headroom() {
+        // A trench far deeper than anything could climb out of.
+        let trench = across(3, -8..=-1, BlockType::Air);
+        let goal = IVec3::new(6, 0, 0);
+        let path = path(IVec3::ZERO, goal, &trench);
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).any(|step| step[0].x == 2 && step[1].x == 4));
+
+        let mut covered = trench.clone();
+        covered.extend((-8..=8).flat_map(|x| across(x, [1], BlockType::Stone)));
+        let path = self::path(IVec3::ZERO, goal, &covered);
+        assert!(path.iter().all(|node| node.x < 3));
+    }
+
+    #[test]
+    fn drops_off_ledges_up_to_max_drop() {
+        let ledge = |height: i32| -> Vec<(IVec3, BlockType)> {
+            (-8..=2)
+                .flat_map(|x| across(x, 0..height, BlockType::Stone))
+                .collect()
+        };
+        let goal = IVec3::new(6, 0, 0);
+
+        let start = IVec3::new(0, MAX_DROP, 0);
+        let path = path(start, goal, &ledge(MAX_DROP));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.contains(&IVec3::new(3, 0, 0)));
+
+        let start = IVec3::new(0, MAX_DROP + 1, 0);
+        let path = self::path(start, goal, &ledge(MAX_DROP + 1));
+        assert!(path.iter().all(|node| node.x <= 2));
+    }
+
+    #[test]
+    fn water_costs_more_to_cross() {
+        let layout = layout(&[(IVec3::new(1, 0, 0), BlockType::Water)]);
+        let terrain = Terrain {
+            height: HEIGHT,
+            block_at: layout.block_at(),
+        };
+        let neighbors = terrain.neighbors(IVec3::ZERO);
+        let cost = |to: IVec3| neighbors.iter().find(|(node, _)| *node == to).unwrap().1;
+        assert_eq!(cost(IVec3::new(-1, 0, 0)), 1.0);
+        assert_eq!(cost(IVec3::new(1, 0, 0)), WATER_COST);
+
+        // A short pool is walked around, but a river all the way across is
+        // swum.
+        let goal = IVec3::new(6, 0, 0);
+        let pool: Vec<_> = (2..=4)
+            .flat_map(|x| (-1..=1).map(move |z| (IVec3::new(x, 0, z), BlockType::Water)))
+            .collect();
+        let path = path(IVec3::ZERO, goal, &pool);
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path
+            .iter()
+            .all(|node| !pool.iter().any(|(water, _)| water == node)));
+
+        let river: Vec<_> = (2..=4)
+            .flat_map(|x| across(x, [0], BlockType::Water))
+            .collect();
+        let path = self::path(IVec3::ZERO, goal, &river);
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.contains(&IVec3::new(3, 0, 0)));
+    }
+
+    #[test]
+    fn diagonals_do_not_cut_corners() {
+        let layout = layout(&[
+            (IVec3::new(1, 0, 0), BlockType::Stone),
+            (IVec3::new(1, 1, 0), BlockType::Stone),
+        ]);
+        let terrain = Terrain {
+            height: HEIGHT,
+            block_at: layout.block_at(),
+        };
+        let neighbors: Vec<IVec3> = terrain
+            .neighbors(IVec3::ZERO)
+            .into_iter()
+            .map(|(node, _)| node)
+            .collect();
+        assert!(!neighbors.contains(&IVec3::new(1, 0, 1)));
+        assert!(!neighbors.contains(&IVec3::new(1, 0, -1)));
+        assert!(neighbors.contains(&IVec3::new(-1, 0, 1)));
+    }
+
+    #[test]
+    fn runs_out_of_budget_and_retries_next_tick() {
+        // Searching for a goal behind the wall floods the whole near side,
+        // which takes more than this tick's budget.
+        let wall = layout(&across(3, [0, 1], BlockType::Stone));
+        let mut pathfinder = Pathfinder::new(100);
+        let walled_off =
+            pathfinder.find_path(IVec3::ZERO, IVec3::new(6, 0, 0), HEIGHT, wall.block_at());
+        assert!(matches!(walled_off, PathResult::Found(_)));
+
+        let goal = IVec3::new(2, 0, 5);
+        assert_eq!(
+            pathfinder.find_path(IVec3::ZERO, goal, HEIGHT, wall.block_at()),
+            PathResult::OutOfBudget
+        );
+        pathfinder.begin_tick();
+        match pathfinder.find_path(IVec3::ZERO, goal, HEIGHT, wall.block_at()) {
+            PathResult::Found(path) => assert_eq!(path.last(), Some(&goal)),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn cached_paths_are_kept_per_body_height() {
+        // A one-high tunnel through a wall across the floor.
+        let mut blocks = across(3, [0, 1], BlockType::Stone);
+        blocks.push((IVec3::new(3, 0, 0), BlockType::Air));
+        let layout = layout(&blocks);
+        let goal = IVec3::new(6, 0, 0);
+        let mut pathfinder = Pathfinder::new(DEFAULT_PATH_BUDGET);
+
+        match pathfinder.find_path(IVec3::ZERO, goal, 1, layout.block_at()) {
+            PathResult::Found(path) => assert_eq!(path.last(), Some(&goal)),
+            other => panic!("{other:?}"),
+        }
+        match pathfinder.find_path(IVec3::ZERO, goal, 2, layout.block_at()) {
+            PathResult::Found(path) => assert!(path.iter().all(|node| node.x < 3)),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn invalidate_drops_cached_paths_near_a_change() {
+        let mut pathfinder = Pathfinder::new(DEFAULT_PATH_BUDGET);
+        let goal = IVec3::new(6, 0, 0);
+        let open = pathfinder.find_path(IVec3::ZERO, goal, HEIGHT, layout(&[]).block_at());
+
+        // Still cached, even though the wall now blocks it.
+        let wall = layout(&across(3, [0, 1], BlockType::Stone));
+        assert_eq!(
+            pathfinder.find_path(IVec3::ZERO, goal, HEIGHT, wall.block_at()),
+            open
+        );
+
+        pathfinder.invalidate(IVec3::new(3, 0, 0));
+        match pathfinder.find_path(IVec3::ZERO, goal, HEIGHT, wall.block_at()) {
+            PathResult::Found(path) => assert!(path.iter().all(|node| node.x < 3)),
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::utils::aabb::Aabb;
+use crate::world::block::BlockType;
+use glam::{IVec3, Vec3};
+
+// In blocks per second squared, and blocks per second.
+pub const GRAVITY: f32 = 28.0;
+pub const TERMINAL_VELOCITY: f32 = 55.0;
+
+/// How far a box actually moved in a `sweep`, and along which axes a block
+/// cut the move short.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sweep {
+    pub offset: Vec3,
+    pub blocked: [bool; 3],
+}
+
+/// Whether bodies collide with the block `block_at` reported. Unloaded blocks
+/// are solid, so nothing falls out of the loaded world while chunks stream in.
+pub fn is_solid(block: Option<BlockType>) -> bool {
+    block.is_none_or(|block| block.is_solid())
+}
+
+/// Moves `bounds` by `motion` through solid blocks, one axis at a time
+/// starting with Y, stopping each axis at the first block in the way.
+/// Everything the box could touch is gathered up front, so a long move can't
+/// tunnel through a thin wall.
+pub fn sweep(bounds: &Aabb, motion: Vec3, block_at: impl Fn(IVec3) -> Option<BlockType>) -> Sweep {
+    let obstacles: Vec<Aabb> = bounds
+        .expand_towards(motion)
+        .block_positions()
+        .filter(|&position| is_solid(block_at(position)))
+        .map(Aabb::from_block)
+        .collect();
+
+    let mut moved = *bounds;
+    let mut sweep = Sweep {
+        offset: Vec3::ZERO,
+        blocked: [false; 3],
+    };
+    for axis in [1, 0, 2] {
+        let distance = obstacles.iter().fold(motion[axis], |distance, obstacle| {
+            obstacle.clip_axis(&moved, axis, distance)
+        });
+        let mut step = Vec3::ZERO;
+        step[axis] = distance;
+        moved = moved.translate(step);
+        sweep.offset[axis] = distance;
+        sweep.blocked[axis] = distance != motion[axis];
+    }
+    sweep
+}
+
+/// How much of the height of `bounds`, from 0 to 1, is under liquid, measured
+/// through the middle of the box.
+pub fn submersion(bounds: &Aabb, block_at: impl Fn(IVec3) -> Option<BlockType>) -> f32 {
+    let height = bounds.max.y - bounds.min.y;
+    if height <= 0.0 {
+        return 0.0;
+    }
+
+    let center = (bounds.min + bounds.max) * 0.5;
+    let (x, z) = (center.x.floor() as i32, center.z.floor() as i32);
+    let submerged: f32 = (bounds.min.y.floor() as i32..bounds.max.y.ceil() as i32)
+        .filter(|&y| block_at(IVec3::new(x, y, z)).is_some_and(|block| block.is_liquid()))
+        .map(|y| {
+            let y = y as f32;
+            bounds.max.y.min(y + 1.0) - bounds.min.y.max(y)
+        })
+        .sum();
+    submerged / height
+}
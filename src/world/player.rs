@@ -0,0 +1,346 @@
+use crate::utils::aabb::Aabb;
+use crate::world::block::BlockType;
+use crate::world::physics::{self, Sweep, GRAVITY, TERMINAL_VELOCITY};
+use glam::{IVec3, Vec3};
+
+pub const PLAYER_HALF_WIDTH: f32 = 0.3;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+pub const PLAYER_EYE_HEIGHT: f32 = 1.6;
+/// Ledges up to this high are walked up without jumping.
+const STEP_HEIGHT: f32 = 1.0;
+
+// Speeds in blocks per second, accelerations in blocks per second squared.
+const WALK_SPEED: f32 = 4.5;
+const RUN_MULTIPLIER: f32 = 1.5;
+const SWIM_MULTIPLIER: f32 = 0.5;
+const GROUND_ACCELERATION: f32 = 50.0;
+const AIR_ACCELERATION: f32 = 10.0;
+const WATER_ACCELERATION: f32 = 15.0;
+/// Enough for a jump of about 1.4 blocks.
+const JUMP_VELOCITY: f32 = 9.0;
+/// Upward acceleration when fully under water. Above gravity, so the player
+/// floats with their head out.
+const BUOYANCY: f32 = GRAVITY * 1.4;
+const SWIM_ACCELERATION: f32 = 20.0;
+/// Fraction of vertical speed lost per second in water.
+const WATER_DRAG: f32 = 4.0;
+
+/// What the player is trying to do this tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    /// Horizontal direction to walk in; zero to stand still.
+    pub direction: Vec3,
+    /// Jump on land, swim up in water.
+    pub jump: bool,
+    /// Swim down in water.
+    pub descend: bool,
+    pub run: bool,
+}
+
+/// A walking body: an upright box with gravity that collides with solid
+/// blocks and swims in liquids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    /// Center of the soles of the player's feet.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    on_ground: bool,
+    submersion: f32,
+}
+
+impl Player {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            submersion: 0.0,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(
+            self.position - Vec3::new(PLAYER_HALF_WIDTH, 0.0, PLAYER_HALF_WIDTH),
+            self.position + Vec3::new(PLAYER_HALF_WIDTH, PLAYER_HEIGHT, PLAYER_HALF_WIDTH),
+        )
+    }
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.position + Vec3::Y * PLAYER_EYE_HEIGHT
+    }
+
+    /// Moves the player so their eyes are at `eye`, at rest. Used to follow
+    /// the free camera while flying.
+    pub fn set_eye_position(&mut self, eye: Vec3) {
+        self.position = eye - Vec3::Y * PLAYER_EYE_HEIGHT;
+        self.velocity = Vec3::ZERO;
+        self.on_ground = false;
+    }
+
+    pub fn is_in_water(&self) -> bool {
+        self.submersion > 0.0
+    }
+
+    /// Advances the player by `dt` seconds against the blocks `block_at`
+    /// reports, where `None` (unloaded) counts as solid.
+    pub fn tick(
+        &mut self,
+        input: &PlayerInput,
+        dt: f32,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) {
+        self.submersion = physics::submersion(&self.bounds(), &block_at);
+        let in_water = self.is_in_water();
+
+        let mut speed = WALK_SPEED;
+        if input.run {
+            speed *= RUN_MULTIPLIER;
+        }
+        if in_water {
+            speed *= SWIM_MULTIPLIER;
+        }
+        let acceleration = if in_water {
+            WATER_ACCELERATION
+        } else if self.on_ground {
+            GROUND_ACCELERATION
+        } else {
+            AIR_ACCELERATION
+        };
+        let target = input.direction.normalize_or_zero() * speed;
+        let horizontal = Vec3::new(self.velocity.x, 0.0, self.velocity.z);
+        let change = (target - horizontal).clamp_length_max(acceleration * dt);
+        self.velocity.x += change.x;
+        self.velocity.z += change.z;
+
+        if in_water {
+            self.velocity.y += (BUOYANCY * self.submersion - GRAVITY) * dt;
+            if input.jump {
+                self.velocity.y += SWIM_ACCELERATION * dt;
+            }
+            if input.descend {
+                self.velocity.y -= SWIM_ACCELERATION * dt;
+            }
+            self.velocity.y *= (1.0 - WATER_DRAG * dt).max(0.0);
+        } else {
+            if input.jump && self.on_ground {
+                self.velocity.y = JUMP_VELOCITY;
+            }
+            self.velocity.y -= GRAVITY * dt;
+        }
+        self.velocity.y = self.velocity.y.max(-TERMINAL_VELOCITY);
+
+        let sweep = self.move_by(self.velocity * dt, in_water, &block_at);
+        self.position += sweep.offset;
+        self.on_ground = sweep.blocked[1] && self.velocity.y <= 0.0;
+        for axis in 0..3 {
+            if sweep.blocked[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+    }
+
+    /// Sweeps the player's box by `motion`. If a wall stops them while they're
+    /// standing or swimming, also tries stepping up onto it and keeps
+    /// whichever gets further.
+    fn move_by(
+        &self,
+        motion: Vec3,
+        in_water: bool,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) -> Sweep {
+        let bounds = self.bounds();
+        let sweep = physics::sweep(&bounds, motion, &block_at);
+        if !(sweep.blocked[0] || sweep.blocked[2]) || !(self.on_ground || in_water) {
+            return sweep;
+        }
+
+        let up = physics::sweep(&bounds, Vec3::Y * STEP_HEIGHT, &block_at);
+        let raised = bounds.translate(up.offset);
+        let across = physics::sweep(&raised, Vec3::new(motion.x, 0.0, motion.z), &block_at);
+        let down = physics::sweep(
+            &raised.translate(across.offset),
+            Vec3::new(0.0, motion.y.min(0.0) - up.offset.y, 0.0),
+            &block_at,
+        );
+
+        let stepped = up.offset + across.offset + down.offset;
+        let horizontal = |offset: Vec3| offset.x * offset.x + offset.z * offset.z;
+        if horizontal(stepped) <= horizontal(sweep.offset) {
+            return sweep;
+        }
+        Sweep {
+            offset: stepped,
+            blocked: [across.blocked[0], down.blocked[1], across.blocked[2]],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::test_layout::Layout;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// A one-block-thick stone floor at y = -1 with air above and below it,
+    /// in every chunk within 64 blocks of the origin. Everything past those
+    /// chunks is unloaded.
+    fn layout() -> Layout {
+        let mut layout = Layout::new(IVec3::splat(-64), IVec3::splat(63));
+        layout.fill(
+            IVec3::new(-64, -1, -64),
+            IVec3::new(63, -1, 63),
+            BlockType::Stone,
+        );
+        layout
+    }
+
+    fn run(
+        player: &mut Player,
+        input: &PlayerInput,
+        ticks: usize,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) {
+        for _ in 0..ticks {
+            player.tick(input, DT, &block_at);
+        }
+    }
+
+    fn standing(position: Vec3, block_at: impl Fn(IVec3) -> Option<BlockType>) -> Player {
+        let mut player = Player::new(position);
+        run(&mut player, &PlayerInput::default(), 30, block_at);
+        player
+    }
+
+    fn walk(direction: Vec3) -> PlayerInput {
+        PlayerInput {
+            direction,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let mut player = Player::new(Vec3::new(0.5, 5.0, 0.5));
+        run(
+            &mut player,
+            &PlayerInput::default(),
+            120,
+            layout().block_at(),
+        );
+        assert!(player.position.y.abs() < 1e-3, "{}", player.position.y);
+        assert!(player.on_ground);
+        assert_eq!(player.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn does_not_tunnel_through_a_thin_floor() {
+        let mut player = Player::new(Vec3::new(0.5, 30.0, 0.5));
+        player.velocity.y = -TERMINAL_VELOCITY;
+        player.tick(&PlayerInput::default(), 1.0, layout().block_at());
+        assert!(player.position.y.abs() < 1e-3, "{}", player.position.y);
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn is_stopped_by_a_wall_at_any_speed() {
+        let mut layout = layout();
+        layout.fill(IVec3::new(3, 0, 0), IVec3::new(3, 2, 0), BlockType::Stone);
+        let block_at = layout.block_at();
+        let mut player = standing(Vec3::new(0.5, 0.0, 0.5), &block_at);
+        player.velocity.x = 1000.0;
+        player.tick(&PlayerInput::default(), 0.5, &block_at);
+        assert!(
+            player.position.x <= 3.0 - PLAYER_HALF_WIDTH + 1e-3,
+            "{}",
+            player.position.x
+        );
+        assert!(player.position.x > 2.0);
+        assert_eq!(player.velocity.x, 0.0);
+    }
+
+    #[test]
+    fn steps_onto_single_blocks_but_not_walls() {
+        // A one-high ledge running from x = 2 to well past where the walk ends.
+        let mut ledge = layout();
+        ledge.fill(IVec3::new(2, 0, 0), IVec3::new(9, 0, 0), BlockType::Stone);
+        let block_at = ledge.block_at();
+        let mut player = standing(Vec3::new(0.5, 0.0, 0.5), &block_at);
+        run(&mut player, &walk(Vec3::X), 60, &block_at);
+        assert!(player.position.x > 2.5, "{}", player.position.x);
+        assert!(
+            (player.position.y - 1.0).abs() < 1e-3,
+            "{}",
+            player.position.y
+        );
+
+        let mut wall = layout();
+        wall.fill(IVec3::new(2, 0, 0), IVec3::new(2, 1, 0), BlockType::Stone);
+        let block_at = wall.block_at();
+        let mut player = standing(Vec3::new(0.5, 0.0, 0.5), &block_at);
+        run(&mut player, &walk(Vec3::X), 60, &block_at);
+        assert!(
+            player.position.x <= 2.0 - PLAYER_HALF_WIDTH + 1e-3,
+            "{}",
+            player.position.x
+        );
+        assert!(player.position.y.abs() < 1e-3, "{}", player.position.y);
+    }
+
+    #[test]
+    fn walks_across_chunk_borders_at_negative_coordinates() {
+        // A ledge whose near face is the border between chunks -1 and -2.
+        let mut layout = layout();
+        layout.fill(
+            IVec3::new(-30, 0, -1),
+            IVec3::new(-17, 0, -1),
+            BlockType::Stone,
+        );
+        let block_at = layout.block_at();
+        let mut player = standing(Vec3::new(-13.5, 0.0, -0.5), &block_at);
+        run(&mut player, &walk(Vec3::NEG_X), 60, &block_at);
+        assert!(player.position.x < -16.5, "{}", player.position.x);
+        assert!(
+            (player.position.y - 1.0).abs() < 1e-3,
+            "{}",
+            player.position.y
+        );
+    }
+
+    #[test]
+    fn jumps_about_one_and_a_half_blocks() {
+        let layout = layout();
+        let block_at = layout.block_at();
+        let mut player = standing(Vec3::new(0.5, 0.0, 0.5), &block_at);
+        let jump = PlayerInput {
+            jump: true,
+            ..Default::default()
+        };
+        player.tick(&jump, DT, &block_at);
+
+        let mut highest: f32 = 0.0;
+        for _ in 0..60 {
+            player.tick(&PlayerInput::default(), DT, &block_at);
+            highest = highest.max(player.position.y);
+        }
+        assert!((1.2..1.6).contains(&highest), "{highest}");
+        assert!(player.position.y.abs() < 1e-3);
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn floats_back_up_to_the_surface() {
+        // A pool 10 deep with its surface at y = 10.
+        let mut layout = layout();
+        layout.fill(IVec3::new(-3, 0, -3), IVec3::new(3, 9, 3), BlockType::Water);
+        let mut player = Player::new(Vec3::new(0.5, 1.0, 0.5));
+        run(&mut player, &PlayerInput::default(), 600, layout.block_at());
+        assert!(player.is_in_water());
+        assert!(player.position.y < 10.0, "{}", player.position.y);
+        assert!(
+            player.eye_position().y > 10.0,
+            "{}",
+            player.eye_position().y
+        );
+    }
+}
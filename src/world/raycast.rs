@@ -0,0 +1,244 @@
+use crate::world::block::BlockType;
+use glam::{IVec3, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// World coordinates of the block that was hit.
+    pub position: IVec3,
+    /// Outward normal of the face the ray entered through; zero if the ray
+    /// started inside the block.
+    pub normal: IVec3,
+    /// Distance along the ray to where it entered the block.
+    pub distance: f32,
+    pub block: BlockType,
+}
+
+/// Which blocks a ray passes through instead of stopping at. Air is always
+/// passed through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaycastFilter {
+    pub skip_liquids: bool,
+    pub skip_transparent: bool,
+}
+
+impl RaycastFilter {
+    fn stops_at(&self, block: BlockType) -> bool {
+        !(block == BlockType::Air
+            || self.skip_liquids && block.is_liquid()
+            || self.skip_transparent && block.is_transparent())
+    }
+}
+
+impl Default for RaycastFilter {
+    fn default() -> Self {
+        Self {
+            skip_liquids: true,
+            skip_transparent: false,
+        }
+    }
+}
+
+/// Walks the block grid from `origin` along `direction` (Amanatides & Woo)
+/// and returns the first block the filter stops at within `max_distance`.
+/// `block_at` returning `None`, e.g. for an unloaded chunk, ends the ray
+/// without a hit.
+pub fn raycast(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    filter: RaycastFilter,
+    block_at: impl Fn(IVec3) -> Option<BlockType>,
+) -> Option<RaycastHit> {
+    let direction = direction.try_normalize()?;
+    let sign = |d: f32| (d > 0.0) as i32 - (d < 0.0) as i32;
+    let step = IVec3::new(sign(direction.x), sign(direction.y), sign(direction.z));
+
+    let mut position = origin.floor().as_ivec3();
+    // Distance along the ray between crossings of each axis' grid lines, and
+    // to the next crossing.
+    let t_delta = direction.recip().abs();
+    let mut t_max = Vec3::select(
+        direction.cmpgt(Vec3::ZERO),
+        (position.as_vec3() + 1.0 - origin) / direction,
+        (origin - position.as_vec3()) / -direction,
+    );
+    t_max = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::INFINITY, t_max);
+
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        let block = block_at(position)?;
+        if filter.stops_at(block) {
+            return Some(RaycastHit {
+                position,
+                normal,
+                distance,
+                block,
+            });
+        }
+
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+        position[axis] += step[axis];
+        distance = t_max[axis];
+        t_max[axis] += t_delta[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::ChunkPos;
+    use crate::world::test_layout::Layout;
+
+    const CENTER: Vec3 = Vec3::splat(0.5);
+
+    /// Air in every chunk within 32 blocks of the origin, with `stone` set.
+    fn layout(stone: &[IVec3]) -> Layout {
+        let mut layout = Layout::new(IVec3::splat(-32), IVec3::splat(32));
+        for &position in stone {
+            layout.set(position, BlockType::Stone);
+        }
+        layout
+    }
+
+    fn cast(
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        layout: &Layout,
+    ) -> Option<RaycastHit> {
+        raycast(
+            origin,
+            direction,
+            max_distance,
+            RaycastFilter::default(),
+            layout.block_at(),
+        )
+    }
+
+    #[test]
+    fn hits_along_each_axis() {
+        let cases = [
+            (Vec3::X, IVec3::new(5, 0, 0), 4.5),
+            (Vec3::NEG_X, IVec3::new(-3, 0, 0), 2.5),
+            (Vec3::Y, IVec3::new(0, 4, 0), 3.5),
+            (Vec3::NEG_Y, IVec3::new(0, -2, 0), 1.5),
+            (Vec3::Z, IVec3::new(0, 0, 7), 6.5),
+            (Vec3::NEG_Z, IVec3::new(0, 0, -2), 1.5),
+        ];
+        for (direction, block, distance) in cases {
+            let hit = cast(CENTER, direction, 10.0, &layout(&[block])).unwrap();
+            assert_eq!(hit.position, block);
+            assert_eq!(hit.normal, -direction.as_ivec3());
+            assert!((hit.distance - distance).abs() < 1e-5, "{direction}");
+            assert_eq!(hit.block, BlockType::Stone);
+        }
+    }
+
+    #[test]
+    fn hits_along_a_diagonal() {
+        // Climbs one block for every two across, entering (4, 2, 0) through
+        // its -X face.
+        let direction = Vec3::new(2.0, 1.0, 0.0);
+        let hit = cast(CENTER, direction, 10.0, &layout(&[IVec3::new(4, 2, 0)])).unwrap();
+        assert_eq!(hit.position, IVec3::new(4, 2, 0));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert!((hit.distance - 3.5 * 1.25f32.sqrt()).abs() < 1e-5);
+
+        // The block above it is just off the line.
+        assert_eq!(
+            cast(CENTER, direction, 5.0, &layout(&[IVec3::new(4, 3, 0)])),
+            None
+        );
+    }
+
+    #[test]
+    fn crosses_chunk_boundaries() {
+        let hit = cast(
+            Vec3::new(15.5, 0.5, 0.5),
+            Vec3::X,
+            10.0,
+            &layout(&[IVec3::new(16, 0, 0)]),
+        )
+        .unwrap();
+        assert_eq!(hit.position, IVec3::new(16, 0, 0));
+        assert!((hit.distance - 0.5).abs() < 1e-5);
+
+        let hit = cast(
+            Vec3::new(-0.5, 0.5, 0.5),
+            Vec3::NEG_X,
+            20.0,
+            &layout(&[IVec3::new(-17, 0, 0)]),
+        )
+        .unwrap();
+        assert_eq!(hit.position, IVec3::new(-17, 0, 0));
+        assert_eq!(hit.normal, IVec3::X);
+        assert!((hit.distance - 15.5).abs() < 1e-5);
+
+        let origin = Vec3::new(-5.5, -3.5, -14.5);
+        let block = IVec3::new(-6, -4, -18);
+        let hit = cast(origin, Vec3::NEG_Z, 10.0, &layout(&[block])).unwrap();
+        assert_eq!(hit.position, block);
+        assert_eq!(hit.normal, IVec3::Z);
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn starting_inside_a_block_hits_it_with_no_normal() {
+        let hit = cast(CENTER, Vec3::X, 10.0, &layout(&[IVec3::ZERO])).unwrap();
+        assert_eq!(hit.position, IVec3::ZERO);
+        assert_eq!(hit.normal, IVec3::ZERO);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn misses_blocks_past_max_distance() {
+        let stone = layout(&[IVec3::new(5, 0, 0)]);
+        assert!(cast(CENTER, Vec3::X, 4.5, &stone).is_some());
+        assert_eq!(cast(CENTER, Vec3::X, 4.4, &stone), None);
+    }
+
+    #[test]
+    fn filters_skip_liquids_and_transparent_blocks() {
+        let mut layout = layout(&[IVec3::new(4, 0, 0)]);
+        layout
+            .set(IVec3::new(2, 0, 0), BlockType::Water)
+            .set(IVec3::new(3, 0, 0), BlockType::Leaves);
+        let hit_with = |skip_liquids, skip_transparent| {
+            let filter = RaycastFilter {
+                skip_liquids,
+                skip_transparent,
+            };
+            raycast(CENTER, Vec3::X, 10.0, filter, layout.block_at()).map(|hit| hit.block)
+        };
+        assert_eq!(hit_with(false, false), Some(BlockType::Water));
+        assert_eq!(hit_with(true, false), Some(BlockType::Leaves));
+        assert_eq!(hit_with(true, true), Some(BlockType::Stone));
+    }
+
+    #[test]
+    fn unloaded_chunks_end_the_ray() {
+        let mut layout = layout(&[IVec3::new(40, 0, 0)]);
+        assert!(cast(CENTER, Vec3::X, 50.0, &layout).is_some());
+        layout.unload(ChunkPos::new(1, 0, 0));
+        assert_eq!(cast(CENTER, Vec3::X, 50.0, &layout), None);
+    }
+
+    #[test]
+    fn zero_direction_hits_nothing() {
+        assert_eq!(
+            cast(CENTER, Vec3::ZERO, 10.0, &layout(&[IVec3::ZERO])),
+            None
+        );
+    }
+}
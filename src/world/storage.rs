@@ -0,0 +1,193 @@
+use crate::world::block::BlockType;
+use crate::world::chunk::{Chunk, ChunkPos, CHUNK_SIZE};
+use crate::world::entity::{Entity, EntityKind};
+use glam::Vec3;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const SAVE_DIR: &str = "saves/world";
+
+const MAGIC: &[u8; 4] = b"VXCH";
+// Version 1 had no entities; those files still load.
+const FORMAT_VERSION: u8 = 2;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+const BLOCK_COUNT: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+/// Kind and kind data bytes, then position, velocity and age as
+/// little-endian f32s.
+const ENTITY_LEN: usize = 2 + 7 * 4;
+/// The count is saved as a u16; entities past it are dropped rather than
+/// corrupting the chunk.
+const MAX_SAVED_ENTITIES: usize = u16::MAX as usize;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Corrupt(PathBuf),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "chunk storage error: {}", e),
+            StorageError::Corrupt(path) => write!(f, "corrupt chunk file {:?}", path),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+/// Chunks that were changed after generation, one file per chunk. Chunks
+/// that were never saved are simply generated again.
+pub struct ChunkStorage {
+    dir: PathBuf,
+}
+
+impl ChunkStorage {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, pos: ChunkPos) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}_{}.chunk", pos.x, pos.y, pos.z))
+    }
+
+    /// Writes `chunk` and marks it as saved.
+    pub fn save(&self, pos: ChunkPos, chunk: &mut Chunk) -> Result<(), StorageError> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + BLOCK_COUNT);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    bytes.push(chunk.get_block(x, y, z).id());
+                }
+            }
+        }
+        let entities = &chunk.entities()[..chunk.entities().len().min(MAX_SAVED_ENTITIES)];
+        bytes.extend_from_slice(&(entities.len() as u16).to_le_bytes());
+        for entity in entities {
+            let (kind, data) = entity.kind.id();
+            bytes.extend_from_slice(&[kind, data]);
+            let floats = entity.position.to_array().into_iter();
+            for value in floats.chain(entity.velocity.to_array()).chain([entity.age]) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        // Written next to the real file and renamed over it, so a crash
+        // mid-write never leaves a truncated chunk behind.
+        let path = self.path(pos);
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, &bytes)?;
+        std::fs::rename(&temp, &path)?;
+
+        chunk.mark_saved();
+        Ok(())
+    }
+
+    /// Reads the chunk at `pos`, or `None` if it was never saved.
+    pub fn load(&self, pos: ChunkPos) -> Result<Option<Chunk>, StorageError> {
+        let path = self.path(pos);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if bytes.len() < HEADER_LEN + BLOCK_COUNT
+            || &bytes[..MAGIC.len()] != MAGIC
+            || !(1..=FORMAT_VERSION).contains(&bytes[MAGIC.len()])
+        {
+            return Err(StorageError::Corrupt(path));
+        }
+        let (blocks, entities) = bytes[HEADER_LEN..].split_at(BLOCK_COUNT);
+
+        let mut chunk = Chunk::new(pos.to_world_pos());
+        let mut ids = blocks.iter();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let id = *ids.next().unwrap();
+                    let block = BlockType::from_id(id)
+                        .ok_or_else(|| StorageError::Corrupt(path.clone()))?;
+                    chunk.set_block(x, y, z, block);
+                }
+            }
+        }
+        let entities = match bytes[MAGIC.len()] {
+            1 if entities.is_empty() => Vec::new(),
+            1 => return Err(StorageError::Corrupt(path)),
+            _ => read_entities(entities).ok_or_else(|| StorageError::Corrupt(path.clone()))?,
+        };
+        for entity in entities {
+            chunk.add_entity(entity);
+        }
+        chunk.mark_saved();
+
+        Ok(Some(chunk))
+    }
+}
+
+/// Parses the entity count and records that follow a chunk's blocks, or
+/// `None` if they're malformed.
+fn read_entities(bytes: &[u8]) -> Option<Vec<Entity>> {
+    let (count, records) = bytes.split_first_chunk::<2>()?;
+    let count = u16::from_le_bytes(*count) as usize;
+    if records.len() != count * ENTITY_LEN {
+        return None;
+    }
+
+    records
+        .chunks_exact(ENTITY_LEN)
+        .map(|record| {
+            let kind = EntityKind::from_id(record[0], record[1])?;
+            let floats: Vec<f32> = record[2..]
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect();
+            let mut entity = Entity::new(
+                kind,
+                Vec3::from_slice(&floats[0..3]),
+                Vec3::from_slice(&floats[3..6]),
+            );
+            entity.age = floats[6];
+            Some(entity)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_past_the_cap_are_dropped_instead_of_corrupting_the_chunk() {
+        let dir = std::env::temp_dir().join(format!("voxel-storage-{}", std::process::id()));
+        let storage = ChunkStorage::new(&dir);
+        let pos = ChunkPos::new(0, 0, 0);
+        let mut chunk = Chunk::new(pos.to_world_pos());
+        chunk.set_block(1, 2, 3, BlockType::Stone);
+        for _ in 0..MAX_SAVED_ENTITIES + 5 {
+            chunk.add_entity(Entity::new(EntityKind::Projectile, Vec3::ONE, Vec3::ZERO));
+        }
+
+        storage.save(pos, &mut chunk).unwrap();
+        let loaded = storage.load(pos).unwrap().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.get_block(1, 2, 3), BlockType::Stone);
+        assert_eq!(loaded.entities().len(), MAX_SAVED_ENTITIES);
+    }
+}
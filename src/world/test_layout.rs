@@ -0,0 +1,68 @@
+use crate::world::access::block_in;
+use crate::world::block::BlockType;
+use crate::world::chunk::{Chunk, ChunkPos};
+use glam::IVec3;
+use std::collections::HashMap;
+
+/// A hand-built block layout for tests, kept in real chunks and read through
+/// `block_in`, so lookups go through the same chunk and local-position math
+/// as the game. Blocks in chunks that aren't loaded read as `None`.
+pub struct Layout {
+    chunks: HashMap<ChunkPos, Chunk>,
+}
+
+impl Layout {
+    /// Loads every chunk overlapping the blocks from `min` to `max`
+    /// inclusive, filled with air.
+    pub fn new(min: IVec3, max: IVec3) -> Self {
+        let min = ChunkPos::from_block_pos(min);
+        let max = ChunkPos::from_block_pos(max);
+        let mut chunks = HashMap::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let pos = ChunkPos::new(x, y, z);
+                    chunks.insert(pos, Chunk::new(pos.to_world_pos()));
+                }
+            }
+        }
+        Self { chunks }
+    }
+
+    /// Panics if the block's chunk isn't loaded.
+    pub fn set(&mut self, position: IVec3, block: BlockType) -> &mut Self {
+        let pos = ChunkPos::from_block_pos(position);
+        let chunk = self
+            .chunks
+            .get_mut(&pos)
+            .unwrap_or_else(|| panic!("{position} is in unloaded chunk {pos:?}"));
+        let [x, y, z] = ChunkPos::local_block_pos(position);
+        chunk.set_block(x, y, z, block);
+        self
+    }
+
+    /// Sets every block from `min` to `max` inclusive.
+    pub fn fill(&mut self, min: IVec3, max: IVec3, block: BlockType) -> &mut Self {
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    self.set(IVec3::new(x, y, z), block);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn unload(&mut self, pos: ChunkPos) -> &mut Self {
+        self.chunks.remove(&pos);
+        self
+    }
+
+    pub fn into_chunks(self) -> HashMap<ChunkPos, Chunk> {
+        self.chunks
+    }
+
+    pub fn block_at(&self) -> impl Fn(IVec3) -> Option<BlockType> + '_ {
+        |position| block_in(&self.chunks, position)
+    }
+}